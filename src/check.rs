@@ -0,0 +1,155 @@
+//! Checks for whoever builds the schedule rather than picks from it, using the
+//! optional teacher, room and curricular year columns:
+//!
+//! ```text
+//! PL:T1:10h00:12h00:Mon:Smith:A1:1
+//! ```
+
+use crate::{shifts::Shifts, util::Class};
+use chrono::NaiveTime;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+/// Two classes that can't both happen as scheduled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clash<'a> {
+    /// A teacher has to be in two classes at once.
+    Teacher(&'a str, [&'a Class; 2]),
+    /// A room has two classes at once.
+    Room(&'a str, [&'a Class; 2]),
+    /// Two classes of the same shift happen at once, so no one can attend both.
+    SameShift([&'a Class; 2]),
+    /// Two courses of the same curricular year have their only theory shifts
+    /// at once, so no student of that year can take both.
+    Year(u32, [&'a Class; 2]),
+}
+
+impl Clash<'_> {
+    pub fn classes(&self) -> [&Class; 2] {
+        match self {
+            Self::Teacher(_, c) | Self::Room(_, c) | Self::SameShift(c) | Self::Year(_, c) => *c,
+        }
+    }
+}
+
+impl Display for Clash<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b] = self.classes();
+        let (start, end) = (a.start.max(b.start), a.end.min(b.end));
+        write!(f, "Lines {} and {}: ", a.line_no, b.line_no)?;
+        let class = |c: &Class| format!("{} {}", c.name, c.kind.code());
+        match self {
+            Self::Teacher(teacher, _) => {
+                write!(f, "{} teaches {} and {}", teacher, class(a), class(b))?
+            }
+            Self::Room(room, _) => write!(f, "{} has {} and {}", room, class(a), class(b))?,
+            Self::SameShift(_) => write!(f, "classes of {} overlap", class(a))?,
+            Self::Year(year, _) => write!(
+                f,
+                "year {} has {} and {}, their only theory shifts,",
+                year,
+                class(a),
+                class(b)
+            )?,
+        }
+        write!(
+            f,
+            " on {} {}-{}",
+            a.weekday,
+            start.format("%H:%M"),
+            end.format("%H:%M")
+        )
+    }
+}
+
+/// When both classes happen, if they ever do.
+fn overlap(a: &Class, b: &Class) -> Option<(NaiveTime, NaiveTime)> {
+    (a.weekday == b.weekday && a.start < b.end && b.start < a.end)
+        .then(|| (a.start.max(b.start), a.end.min(b.end)))
+}
+
+/// Every clash in `schedule`, by the lines of the classes. A line written
+/// twice isn't a clash with itself, that's for [`lint`](crate::lint) to report.
+pub fn check(schedule: &Shifts) -> Vec<Clash<'_>> {
+    let mut theory_shifts = BTreeMap::<&str, usize>::new();
+    for s in schedule.shifts().filter(|s| s.kind.unique_id() > 0) {
+        *theory_shifts.entry(s.course.as_str()).or_default() += 1;
+    }
+    let only_theory = |c: &Class| c.kind.unique_id() > 0 && theory_shifts[c.name.as_str()] == 1;
+    let classes = schedule.classes();
+    let mut clashes = Vec::new();
+    for (i, a) in classes.iter().enumerate() {
+        let same_class = |b: &&Class| {
+            (&a.name, a.kind, a.weekday, a.start, a.end)
+                == (&b.name, b.kind, b.weekday, b.start, b.end)
+        };
+        for b in classes[i + 1..]
+            .iter()
+            .filter(|b| overlap(a, b).is_some() && !same_class(b))
+        {
+            let pair = [a, b];
+            if let (Some(t), Some(u)) = (&a.teacher, &b.teacher) {
+                if t == u {
+                    clashes.push(Clash::Teacher(t, pair));
+                }
+            }
+            if let (Some(r), Some(s)) = (&a.room, &b.room) {
+                if r == s {
+                    clashes.push(Clash::Room(r, pair));
+                }
+            }
+            if a.name == b.name && a.kind == b.kind {
+                clashes.push(Clash::SameShift(pair));
+            }
+            if let (Some(y), Some(z)) = (a.year, b.year) {
+                if y == z && a.name != b.name && only_theory(a) && only_theory(b) {
+                    clashes.push(Clash::Year(y, pair));
+                }
+            }
+        }
+    }
+    clashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clashes() {
+        let schedule = "PL:T1:10h00:12h00:Mon:Smith:A1:1\n\
+                        PL:T1:11h00:12h00:Mon\n\
+                        CPD:T1:11h30:13h00:Mon:Smith:A2:1\n\
+                        AI:T1:11h00:11h30:Mon:Jones:A1:2\n\
+                        AI:T2:11h00:11h30:Mon:Smith:A3:2\n\
+                        PL:T1:10h00:12h00:Tue:Smith:A1:1"
+            .parse()
+            .unwrap();
+        let clashes = check(&schedule)
+            .iter()
+            .map(Clash::to_string)
+            .collect::<Vec<_>>();
+        // AI has two theory shifts, so year 2 can take either.
+        assert_eq!(
+            clashes,
+            vec![
+                "Lines 1 and 2: classes of PL T1 overlap on Mon 11:00-12:00",
+                "Lines 1 and 3: Smith teaches PL T1 and CPD T1 on Mon 11:30-12:00",
+                "Lines 1 and 3: year 1 has PL T1 and CPD T1, their only theory shifts, on Mon 11:30-12:00",
+                "Lines 1 and 4: A1 has PL T1 and AI T1 on Mon 11:00-11:30",
+                "Lines 1 and 5: Smith teaches PL T1 and AI T2 on Mon 11:00-11:30",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_lines_dont_clash() {
+        let schedule = "PL:T1:10h00:12h00:Mon:Smith:A1:1\n\
+                        PL:T1:10h00:12h00:Mon:Smith:A1:1"
+            .parse()
+            .unwrap();
+        assert_eq!(check(&schedule), vec![]);
+    }
+}
@@ -0,0 +1,491 @@
+//! The subcommands that aren't the interactive mode.
+
+use crate::{load_schedule, menu::parse_time, option, parse_option, usage};
+use chrono::{NaiveDate, NaiveTime};
+use scheduler::{
+    check, diff, enrolment,
+    free::{self, FreeTimeQuery},
+    group::{self, GroupWeights},
+    ical,
+    lint::{self, Level, LintLevels},
+    matrix::ConflictMatrix,
+    replan,
+    swap::{self, SwapPlan},
+    vacancy::Vacancies,
+    ClassType, Comparison, Ranking, RenderOptions, Theme, TimeTable, TimetableFilters,
+    TimetableKey, WeekDay,
+};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io,
+    path::Path,
+    process,
+};
+
+/// `group <schedule>[,<filters>]... [--top N] [--weights SHIFTS,FREE_TIME]`
+///
+/// Picks a timetable for each student, each given by their schedule and
+/// optionally the filters they saved, so that they share as much as possible.
+pub fn group(args: &mut Vec<String>, theme: &Theme, options: &RenderOptions) -> io::Result<()> {
+    let top = parse_option(args, "--top", "--top needs a number")?.unwrap_or(3);
+    let weights = match option(args, "--weights") {
+        Some(w) => match w.split_once(',').map(|(s, f)| (s.parse(), f.parse())) {
+            Some((Ok(shifts), Ok(free_time))) => GroupWeights { shifts, free_time },
+            _ => return Err(usage("--weights needs two numbers, like 4,1")),
+        },
+        None => GroupWeights::default(),
+    };
+    let mut names = Vec::new();
+    let mut schedules = Vec::new();
+    let mut filters = Vec::new();
+    for arg in &args[1..] {
+        let (path, filter_path) = match arg.split_once(',') {
+            Some((path, f)) => (path, Some(f)),
+            None => (arg.as_str(), None),
+        };
+        names.push(
+            Path::new(path)
+                .file_stem()
+                .map_or(path.into(), |s| s.to_string_lossy().into_owned()),
+        );
+        schedules.push(load_schedule(path)?);
+        filters.push(match filter_path {
+            Some(f) => serde_json::from_reader(File::open(f)?)?,
+            None => TimetableFilters::default(),
+        });
+    }
+    if schedules.len() < 2 {
+        return Err(usage("group needs the schedules of at least two students"));
+    }
+    let all = schedules
+        .iter()
+        .map(TimeTable::all_the_combos)
+        .collect::<Vec<_>>();
+    let candidates = all
+        .iter()
+        .zip(&filters)
+        .map(|(tts, f)| {
+            let mut remaining = f.apply(tts);
+            Ranking::default().sort(&mut remaining);
+            remaining
+        })
+        .collect::<Vec<_>>();
+    for (name, c) in names.iter().zip(&candidates) {
+        println!("{}: {} timetables", name, c.len());
+    }
+    let styles = theme.styles(
+        schedules
+            .iter()
+            .flat_map(|s| s.courses())
+            .collect::<BTreeSet<_>>(),
+    );
+    for (i, choice) in group::best_choices(&candidates, weights, top)
+        .iter()
+        .enumerate()
+    {
+        println!(
+            "\nChoice {}: score {}, {} shared shifts, {}h{:02} of free time in common",
+            i + 1,
+            choice.score,
+            choice.shared_shifts.len(),
+            choice.shared_free_minutes / 60,
+            choice.shared_free_minutes % 60,
+        );
+        for ((course, kind), who) in &choice.shared_shifts {
+            let who = who.iter().map(|w| names[*w].as_str()).collect::<Vec<_>>();
+            println!("  {} {}: {}", course, kind.code(), who.join(", "));
+        }
+        let comparison = Comparison::new(
+            names
+                .iter()
+                .cloned()
+                .zip(choice.timetables.iter().copied())
+                .collect(),
+        );
+        print!("{}", comparison.side_by_side(&styles, options));
+    }
+    Ok(())
+}
+
+/// `free <schedule>... [--ics <calendar>]... [--min MINUTES] [--days Mon,Tue]
+/// [--from TIME] [--until TIME] [--ical <file> --start DATE --end DATE]`
+///
+/// Lists the weekly windows where no one is busy, going by every class in the
+/// schedules, like those exported with the chosen shifts, and every event in
+/// the calendars.
+pub fn free(args: &mut Vec<String>, theme: &Theme, options: &RenderOptions) -> io::Result<()> {
+    let mut query = FreeTimeQuery::default();
+    if let Some(min) = parse_option(args, "--min", "--min needs a number of minutes")? {
+        query.min_minutes = min;
+    }
+    if let Some(days) = option(args, "--days") {
+        query.days = days
+            .split(',')
+            .map(|d| d.parse::<WeekDay>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| usage("--days needs days like Mon,Tue"))?;
+    }
+    query.from = time_option(args, "--from")?.unwrap_or(query.from);
+    query.until = time_option(args, "--until")?.unwrap_or(query.until);
+    let export = option(args, "--ical");
+    let mut date = |name| parse_option::<NaiveDate>(args, name, "Dates have to be like 2020-02-10");
+    let (start, end) = (date("--start")?, date("--end")?);
+
+    let mut busy = Vec::new();
+    while let Some(path) = option(args, "--ics") {
+        busy.push(free::busy_from_ical(&fs::read_to_string(path)?).map_err(io::Error::other)?);
+    }
+    for path in &args[1..] {
+        busy.push(free::busy_from_schedule(&load_schedule(path)?));
+    }
+    let busy = free::everyone(&busy);
+    let windows = free::free_windows(&busy, &query);
+    for w in &windows {
+        println!(
+            "{} {}-{} ({}h{:02})",
+            w.weekday,
+            w.start.format("%H:%M"),
+            w.end.format("%H:%M"),
+            w.minutes() / 60,
+            w.minutes() % 60
+        );
+    }
+    let styles = theme.styles(vec!["busy", "free"]);
+    print!(
+        "{}",
+        free::free_grid(&busy, &windows, styles["free"], styles["busy"]).render(options)
+    );
+    if let Some(path) = export {
+        match (start, end) {
+            (Some(start), Some(end)) => {
+                ical::write_free_cal(&mut File::create(path)?, &windows, start, end)?
+            }
+            _ => return Err(usage("--ical needs --start and --end dates")),
+        }
+    }
+    Ok(())
+}
+
+/// `swap <schedule> <enrolment> [--filters <file>] [--free-day DAY]...
+/// [--has COURSE:SHIFT]... [--not COURSE:SHIFT]... [--starts-after TIME]
+/// [--ends-before TIME] [--top N]`
+///
+/// Lists the fewest shift swaps that get from the enrolment to a timetable that
+/// passes the target filters.
+pub fn swap(args: &mut Vec<String>, theme: &Theme, options: &RenderOptions) -> io::Result<()> {
+    let top = parse_option(args, "--top", "--top needs a number")?.unwrap_or(5);
+    let target = target_filters(args)?;
+    if target == TimetableFilters::default() {
+        return Err(usage(
+            "swap needs a target, such as --free-day Fri or --not PL:L3",
+        ));
+    }
+    let (schedule, enrolment) = match &args[1..] {
+        [schedule, enrolment] => (load_schedule(schedule)?, fs::read_to_string(enrolment)?),
+        _ => return Err(usage("swap needs a schedule and an enrolment")),
+    };
+    let current = parse_enrolment(&enrolment)?;
+    let timetables = TimeTable::all_the_combos(&schedule);
+    let styles = theme.styles(schedule.courses());
+    let plans = swap::find_swaps(&current, &target, &timetables, top);
+    if plans.is_empty() && top > 0 {
+        println!("No timetable passes the target");
+    }
+    for (i, plan) in plans.iter().enumerate() {
+        print_plan(i, plan);
+        print!("{}", plan.timetable.render(&styles, options));
+    }
+    Ok(())
+}
+
+/// `diff <old schedule> <new schedule>` and the filters of `swap`
+///
+/// Lists what changed between two versions of a schedule, marking the changes
+/// to shifts used by the timetables of the old one that pass the filters.
+pub fn diff(args: &mut Vec<String>, theme: &Theme) -> io::Result<()> {
+    let filters = target_filters(args)?;
+    let (old, new) = match &args[1..] {
+        [old, new] => (load_schedule(old)?, load_schedule(new)?),
+        _ => return Err(usage("diff needs the old and the new schedule")),
+    };
+    let timetables = TimeTable::all_the_combos(&old);
+    let remaining = filters.apply(&timetables);
+    let changes = diff::diff(&old, &new);
+    if changes.is_empty() {
+        println!("No changes");
+    }
+    let highlight = theme.highlight();
+    for change in changes {
+        match change.affects(&remaining) {
+            0 => println!("  {}", change),
+            n => println!(
+                "{} {}",
+                highlight.fill(1),
+                highlight.paint(
+                    false,
+                    format!("{} ({} of {} timetables)", change, n, remaining.len())
+                )
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// `check <schedule>`
+///
+/// Lists the teachers and rooms booked twice at once, the shifts that overlap
+/// themselves and the courses of a curricular year whose only theory shifts
+/// clash, exiting with an error if there are any.
+pub fn check(args: &[String]) -> io::Result<()> {
+    let schedule = match &args[1..] {
+        [schedule] => load_schedule(schedule)?,
+        _ => return Err(usage("check needs a schedule")),
+    };
+    for c in schedule.classes() {
+        if let Some(year) = c.invalid_year() {
+            println!(
+                "Line {}: warning: {} isn't a curricular year, so it's left out",
+                c.line_no, year
+            );
+        }
+    }
+    let clashes = check::check(&schedule);
+    for clash in &clashes {
+        println!("{}", clash);
+    }
+    match clashes.len() {
+        0 => println!("No clashes"),
+        n => {
+            println!("{} clash{}", n, if n == 1 { "" } else { "es" });
+            process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// `lint <schedule> [--allow RULE]... [--warn RULE]... [--deny RULE]...`
+///
+/// Warns about the mistakes a schedule parses fine with, exiting with an error
+/// if any lint is denied.
+pub fn lint(args: &mut Vec<String>) -> io::Result<()> {
+    let mut levels = LintLevels::default();
+    for (flag, level) in &[
+        ("--allow", Level::Allow),
+        ("--warn", Level::Warn),
+        ("--deny", Level::Deny),
+    ] {
+        while let Some(rule) = option(args, flag) {
+            levels.set(rule.parse().map_err(usage)?, *level);
+        }
+    }
+    let schedule = match &args[1..] {
+        [schedule] => load_schedule(schedule)?,
+        _ => return Err(usage("lint needs a schedule")),
+    };
+    let lints = lint::lint(&schedule, &levels);
+    for l in &lints {
+        println!("{}", l);
+    }
+    let errors = lints.iter().filter(|l| l.level == Level::Deny).count();
+    let warnings = lints.len() - errors;
+    println!(
+        "{} error{}, {} warning{}",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" }
+    );
+    if errors > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// `matrix <schedule> [--csv <file>] [--json <file>]`
+///
+/// Shows how often each pair of courses clash, and exports which shifts do.
+pub fn matrix(args: &mut Vec<String>) -> io::Result<()> {
+    let csv = option(args, "--csv");
+    let json = option(args, "--json");
+    let schedule = match &args[1..] {
+        [schedule] => load_schedule(schedule)?,
+        _ => return Err(usage("matrix needs a schedule")),
+    };
+    let matrix = ConflictMatrix::new(&schedule);
+    print!("{}", matrix);
+    if let Some(path) = csv {
+        matrix.write_csv(File::create(path)?)?;
+    }
+    if let Some(path) = json {
+        matrix.write_json(File::create(path)?)?;
+    }
+    Ok(())
+}
+
+/// `replan <schedule> <enrolment> [--old <schedule>] [--top N]` and the
+/// filters of `swap`
+///
+/// Shows which enrolled shifts moved since the `--old` schedule, or since the
+/// enrolment when it's a schedule file, which of them clash now, and the
+/// timetables that pass the filters closest to the enrolment.
+pub fn replan(args: &mut Vec<String>, theme: &Theme, options: &RenderOptions) -> io::Result<()> {
+    let top = parse_option(args, "--top", "--top needs a number")?.unwrap_or(3);
+    let old = option(args, "--old")
+        .map(|p| load_schedule(&p))
+        .transpose()?;
+    let filters = target_filters(args)?;
+    let (schedule, enrolment) = match &args[1..] {
+        [schedule, enrolment] => (load_schedule(schedule)?, fs::read_to_string(enrolment)?),
+        _ => return Err(usage("replan needs the new schedule and an enrolment")),
+    };
+    let current = parse_enrolment(&enrolment)?;
+    let old = old.or_else(|| enrolment.parse().ok());
+    match old {
+        Some(old) => {
+            let moved = replan::moved(&old, &schedule, &current);
+            if moved.is_empty() {
+                println!("None of the enrolled shifts moved");
+            }
+            for m in moved {
+                println!("Moved {}", m);
+            }
+        }
+        None => println!("Pass the old schedule with --old to see what moved"),
+    }
+    let conflicts = replan::conflicts(&schedule, &current);
+    if conflicts.is_empty() {
+        println!("The enrolled shifts still fit together");
+    }
+    for c in conflicts {
+        println!("Clash on {}", c);
+    }
+    let timetables = TimeTable::all_the_combos(&schedule);
+    let styles = theme.styles(schedule.courses());
+    let plans = replan::closest(&current, &filters.apply(&timetables));
+    if plans.is_empty() {
+        println!("No timetable passes the filters");
+    }
+    for (i, plan) in plans.iter().take(top).enumerate() {
+        print_plan(i, plan);
+        print!("{}", plan.timetable.render(&styles, options));
+    }
+    Ok(())
+}
+
+fn parse_enrolment(contents: &str) -> io::Result<TimetableKey> {
+    enrolment::parse_enrolment(contents).map_err(|e| {
+        eprintln!("{}", e);
+        io::Error::from(io::ErrorKind::Other)
+    })
+}
+
+fn print_plan(i: usize, plan: &SwapPlan) {
+    println!(
+        "\nOption {}: {} swap{}",
+        i + 1,
+        plan.swaps.len(),
+        if plan.swaps.len() == 1 { "" } else { "s" }
+    );
+    for s in &plan.swaps {
+        println!("  {}", s);
+    }
+}
+
+/// `vacancies <schedule> <vacancies> [--top N]` and the filters of `swap`
+///
+/// Lists the timetables that can still be had, safest first, and what to swap
+/// to if a shift of the first one fills up.
+pub fn vacancies(args: &mut Vec<String>, theme: &Theme, options: &RenderOptions) -> io::Result<()> {
+    let top = parse_option(args, "--top", "--top needs a number")?.unwrap_or(3);
+    let filters = target_filters(args)?;
+    let (schedule, vacancies) = match &args[1..] {
+        [schedule, vacancies] => (load_schedule(schedule)?, Vacancies::load(vacancies)?),
+        _ => return Err(usage("vacancies needs a schedule and a vacancies file")),
+    };
+    let open = vacancies.open_shifts(&schedule);
+    let left = open.class_set();
+    for (course, theory) in schedule.class_set().keys() {
+        if !left.contains_key(&(*course, *theory)) {
+            let kind = if *theory { "theory" } else { "lab" };
+            println!("Every {} shift of {} is full", kind, course);
+        }
+    }
+    let timetables = TimeTable::all_the_combos(&open);
+    let mut remaining = filters.apply(&timetables);
+    Ranking::default().sort(&mut remaining);
+    vacancies.sort_by_risk(&mut remaining);
+    let styles = theme.styles(schedule.courses());
+    for (i, t) in remaining.iter().enumerate().take(top) {
+        println!(
+            "\n#{}: {:.0}% risk of missing a shift",
+            i + 1,
+            vacancies.risk(t) * 100.0
+        );
+        print!("{}", t.render(&styles, options));
+    }
+    let preferred = match remaining.first() {
+        Some(t) => t,
+        None => {
+            println!("No timetable with seats left passes the filters");
+            return Ok(());
+        }
+    };
+    for fallback in vacancies.fallbacks(preferred, &remaining) {
+        println!(
+            "\nIf {} {} fills up ({} seat{} left):",
+            fallback.course,
+            fallback.kind.code(),
+            fallback.seats,
+            if fallback.seats == 1 { "" } else { "s" }
+        );
+        match fallback.plan {
+            Some(plan) => {
+                for s in &plan.swaps {
+                    println!("  {}", s);
+                }
+            }
+            None => println!("  no other timetable passes the filters"),
+        }
+    }
+    Ok(())
+}
+
+/// The filters in `--filters`, with those given one by one added to them.
+fn target_filters(args: &mut Vec<String>) -> io::Result<TimetableFilters> {
+    let mut filters = match option(args, "--filters") {
+        Some(path) => serde_json::from_reader(File::open(path)?)?,
+        None => TimetableFilters::default(),
+    };
+    while let Some(day) = parse_option(args, "--free-day", "--free-day needs a day like Fri")? {
+        filters.free_days.insert(day);
+    }
+    while let Some(shift) = option(args, "--has") {
+        filters.has_the_shift.push(shift_arg(&shift)?);
+    }
+    while let Some(shift) = option(args, "--not") {
+        filters.hasnt_the_shift.push(shift_arg(&shift)?);
+    }
+    if let Some(t) = time_option(args, "--starts-after")? {
+        filters.starts_after = Some(t);
+    }
+    if let Some(t) = time_option(args, "--ends-before")? {
+        filters.ends_before = Some(t);
+    }
+    Ok(filters)
+}
+
+/// `PL:L3`.
+fn shift_arg(s: &str) -> io::Result<(ClassType, String)> {
+    match s.split_once(':').map(|(c, k)| (c, k.parse())) {
+        Some((course, Ok(kind))) => Ok((kind, course.to_string())),
+        _ => Err(usage(format!("Expected a shift like PL:L3, got {}", s))),
+    }
+}
+
+/// A time like `18`, `18h30` or `18:30`.
+fn time_option(args: &mut Vec<String>, name: &str) -> io::Result<Option<NaiveTime>> {
+    option(args, name)
+        .map(|t| parse_time(&t).map_err(|_| usage(format!("{} needs a time like 18h30", name))))
+        .transpose()
+}
@@ -0,0 +1,277 @@
+//! Putting a few timetables next to each other to see where they differ.
+
+use crate::{
+    render::{self, Cell, Grid, RenderOptions},
+    theme::CourseStyle,
+    timetable::{Metrics, TimeTable, SLOTS},
+    util::{ClassType, ALL_DAYS},
+};
+use chrono::{NaiveTime, Timelike};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+    ops::Range,
+};
+
+/// A metric's name, how to get it and how to show it.
+type MetricRow = (&'static str, fn(&Metrics) -> Option<i64>, fn(i64) -> String);
+
+/// A few timetables, each with the label it's shown with, like `#3`.
+#[derive(Debug, Clone)]
+pub struct Comparison<'a> {
+    timetables: Vec<(String, &'a TimeTable)>,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(timetables: Vec<(String, &'a TimeTable)>) -> Self {
+        Self { timetables }
+    }
+
+    /// The shifts of each timetable for the courses where not all of them
+    /// picked the same ones.
+    pub fn differing_shifts(&self) -> BTreeMap<&'a str, Vec<BTreeSet<ClassType>>> {
+        let shifts = self
+            .timetables
+            .iter()
+            .map(|(_, t)| t.shifts())
+            .collect::<Vec<_>>();
+        let courses = shifts
+            .iter()
+            .flat_map(|s| s.keys().copied())
+            .collect::<BTreeSet<_>>();
+        courses
+            .into_iter()
+            .map(|course| {
+                let picked = shifts
+                    .iter()
+                    .map(|s| s.get(course).cloned().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                (course, picked)
+            })
+            .filter(|(_, picked)| picked.windows(2).any(|w| w[0] != w[1]))
+            .collect()
+    }
+
+    /// The grids of every timetable next to each other, showing the same days
+    /// and hours so their rows line up.
+    pub fn side_by_side(
+        &self,
+        styles: &BTreeMap<&str, CourseStyle>,
+        options: &RenderOptions,
+    ) -> String {
+        let grids = self
+            .timetables
+            .iter()
+            .map(|(_, t)| t.grid(styles))
+            .collect::<Vec<_>>();
+        let days = ALL_DAYS
+            .iter()
+            .copied()
+            .filter(|d| grids.iter().any(|g| g.days(options).contains(d)))
+            .collect::<Vec<_>>();
+        let rows = grids
+            .iter()
+            .map(|g| g.rows(&days))
+            .filter(|r| !r.is_empty())
+            .fold(None, |acc: Option<Range<usize>>, r| {
+                Some(acc.map_or(r.clone(), |a| a.start.min(r.start)..a.end.max(r.end)))
+            })
+            .unwrap_or(0..0);
+        const GAP: usize = 3;
+        let n = grids.len().max(1);
+        let options = RenderOptions {
+            width: options.width.map(|w| w.saturating_sub(GAP * (n - 1)) / n),
+            ..*options
+        };
+        let blocks = self
+            .timetables
+            .iter()
+            .zip(&grids)
+            .map(|((label, _), g)| {
+                format!(
+                    "{}\n{}",
+                    label,
+                    g.render_within(&options, &days, rows.clone())
+                )
+            })
+            .collect::<Vec<_>>();
+        render::side_by_side(&blocks, GAP)
+    }
+
+    /// A single grid with the half hours every timetable agrees on drawn as
+    /// usual, and those where they differ drawn with `highlight`, listing what
+    /// each one has there.
+    pub fn overlay(&self, styles: &BTreeMap<&str, CourseStyle>, highlight: CourseStyle) -> Grid {
+        let grids = self
+            .timetables
+            .iter()
+            .map(|(_, t)| t.grid(styles))
+            .collect::<Vec<_>>();
+        let mut overlay = Grid::default();
+        for day in ALL_DAYS.iter().copied() {
+            for slot in 0..SLOTS {
+                let cells = grids.iter().map(|g| g.get(day, slot)).collect::<Vec<_>>();
+                let cell = if cells.windows(2).all(|w| w[0] == w[1]) {
+                    cells.first().copied().flatten().cloned()
+                } else {
+                    Some(Cell {
+                        text: cells
+                            .iter()
+                            .map(|c| c.map_or("-", |c| c.text.as_str()))
+                            .collect::<Vec<_>>()
+                            .join("/"),
+                        style: highlight,
+                        theory: true,
+                    })
+                };
+                if let Some(cell) = cell {
+                    overlay.set(day, slot, cell);
+                }
+            }
+        }
+        let courses = self
+            .timetables
+            .iter()
+            .flat_map(|(_, t)| t.shifts().into_keys())
+            .collect::<BTreeSet<_>>();
+        for course in courses {
+            if let Some(style) = styles.get(course) {
+                overlay.add_legend(course, *style);
+            }
+        }
+        overlay.add_legend("differs", highlight);
+        overlay
+    }
+}
+
+/// The shifts that differ, then a table of metrics where every timetable after
+/// the first also shows how far it is from the first.
+impl Display for Comparison<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labels = self.timetables.iter().map(|(l, _)| l.as_str());
+        let differing = self.differing_shifts();
+        if differing.is_empty() {
+            writeln!(f, "Same shifts")?;
+        } else {
+            writeln!(f, "Shifts that differ:")?;
+            for (course, picked) in differing {
+                write!(f, "  {}:", course)?;
+                for (label, kinds) in labels.clone().zip(picked) {
+                    let kinds = kinds.iter().map(|k| k.code()).collect::<Vec<_>>();
+                    write!(f, "  {} {}", label, kinds.join(" "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        let metrics = self
+            .timetables
+            .iter()
+            .map(|(_, t)| t.metrics())
+            .collect::<Vec<_>>();
+        let rows: [MetricRow; 6] = [
+            (
+                "Days on campus",
+                |m| Some(m.days_on_campus.into()),
+                |n| n.to_string(),
+            ),
+            ("Class time", |m| Some(m.class_minutes.into()), duration),
+            ("Campus time", |m| Some(m.campus_minutes.into()), duration),
+            ("Gaps", |m| Some(m.gap_minutes.into()), duration),
+            (
+                "Earliest start",
+                |m| m.earliest_start.map(minutes),
+                duration,
+            ),
+            ("Latest end", |m| m.latest_end.map(minutes), duration),
+        ];
+        let label_width = rows
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        write!(f, "{:w$}", "", w = label_width)?;
+        for label in labels {
+            write!(f, "  {:14}", label)?;
+        }
+        writeln!(f)?;
+        for (name, value, show) in rows.iter() {
+            write!(f, "{:w$}", name, w = label_width)?;
+            let first = metrics.first().and_then(value);
+            for m in &metrics {
+                let cell = match (value(m), first) {
+                    (None, _) => "-".to_string(),
+                    (Some(v), Some(first)) if v != first => {
+                        let sign = if v > first { '+' } else { '-' };
+                        format!("{} ({}{})", show(v), sign, show((v - first).abs()))
+                    }
+                    (Some(v), _) => show(v),
+                };
+                write!(f, "  {:14}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn minutes(t: NaiveTime) -> i64 {
+    i64::from(t.num_seconds_from_midnight() / 60)
+}
+
+/// Minutes as `4h30`, used for both times of the day and durations.
+fn duration(minutes: i64) -> String {
+    format!("{}h{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::WeekDay;
+
+    #[test]
+    fn differences() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:14h00:15h00:Mon\n\
+                        B:T1:11h00:12h00:Mon"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let comparison = Comparison::new(vec![
+            ("#1".to_string(), &timetables[0]),
+            ("#2".to_string(), &timetables[1]),
+        ]);
+        let differing = comparison.differing_shifts();
+        assert_eq!(differing.keys().collect::<Vec<_>>(), vec![&"A"]);
+        assert_eq!(
+            differing["A"],
+            vec![
+                BTreeSet::from([ClassType::T(1)]),
+                BTreeSet::from([ClassType::T(2)])
+            ]
+        );
+        let table = comparison.to_string();
+        assert!(table.starts_with("Shifts that differ:\n  A:  #1 T1  #2 T2\n"));
+        assert!(table.contains("Campus time     2h00            4h00 (+2h00)"));
+        assert!(table.contains("Gaps            0h00            2h00 (+2h00)"));
+        assert!(table.contains("Earliest start  10h00           11h00 (+1h00)"));
+
+        let highlight = CourseStyle {
+            color: None,
+            pattern: Some('#'),
+            second_pattern: None,
+        };
+        let overlay = comparison.overlay(&BTreeMap::new(), highlight);
+        let differs = overlay.get(WeekDay::Mon, 20).unwrap();
+        assert_eq!(differs.text, "T1 A/-");
+        assert_eq!(differs.style, highlight);
+        assert_eq!(overlay.get(WeekDay::Mon, 28).unwrap().text, "-/T2 A");
+        assert_eq!(overlay.get(WeekDay::Mon, 22).unwrap().text, "T1 B");
+
+        let same = Comparison::new(vec![
+            ("#1".to_string(), &timetables[0]),
+            ("#1".to_string(), &timetables[0]),
+        ]);
+        assert!(same.to_string().starts_with("Same shifts\n"));
+    }
+}
@@ -0,0 +1,217 @@
+//! What changed between two versions of a schedule, shift by shift.
+//!
+//! A course that is gone from the old version and one that is new in the other
+//! are taken to be the same course renamed when they have the very same shifts
+//! at the very same times.
+
+use crate::{
+    shifts::{format_times, Shifts, Times},
+    timetable::TimeTable,
+    util::ClassType,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added {
+        course: String,
+        kind: ClassType,
+        times: Times,
+    },
+    Removed {
+        course: String,
+        kind: ClassType,
+        times: Times,
+    },
+    /// A shift whose classes are on other days or at other times.
+    Moved {
+        course: String,
+        kind: ClassType,
+        before: Times,
+        after: Times,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+}
+
+impl Change {
+    /// How many of `timetables`, made from the old version, are made with
+    /// what changed. Shifts that were added never are.
+    pub fn affects(&self, timetables: &[&TimeTable]) -> usize {
+        let uses = |t: &TimeTable| match self {
+            Self::Added { .. } => false,
+            Self::Removed { course, kind, .. } | Self::Moved { course, kind, .. } => {
+                t.has_the_shift(*kind, course)
+            }
+            Self::Renamed { from, .. } => t.chosen().iter().any(|s| s.course == *from),
+        };
+        timetables.iter().filter(|t| uses(t)).count()
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Added {
+                course,
+                kind,
+                times,
+            } => write!(f, "+ {} {}: {}", course, kind.code(), format_times(times)),
+            Self::Removed {
+                course,
+                kind,
+                times,
+            } => write!(f, "- {} {}: {}", course, kind.code(), format_times(times)),
+            Self::Moved {
+                course,
+                kind,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ {} {}: {} -> {}",
+                course,
+                kind.code(),
+                format_times(before),
+                format_times(after)
+            ),
+            Self::Renamed { from, to } => write!(f, "= {} renamed to {}", from, to),
+        }
+    }
+}
+
+/// The shifts of each course, with their times.
+fn by_course(schedule: &Shifts) -> BTreeMap<&str, BTreeMap<ClassType, Times>> {
+    let mut courses = BTreeMap::<_, BTreeMap<_, _>>::new();
+    for s in schedule.shifts() {
+        courses
+            .entry(s.course.as_str())
+            .or_default()
+            .insert(s.kind, s.times());
+    }
+    courses
+}
+
+/// The changes from `old` to `new`, renames first and then by course and
+/// shift.
+pub fn diff(old: &Shifts, new: &Shifts) -> Vec<Change> {
+    let (mut before, mut after) = (by_course(old), by_course(new));
+    let mut changes = Vec::new();
+    let gone = before
+        .keys()
+        .filter(|c| !after.contains_key(*c))
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for from in gone {
+        let renamed = after
+            .iter()
+            .find(|(to, shifts)| !before.contains_key(*to) && **shifts == before[from])
+            .map(|(to, _)| *to);
+        if let Some(to) = renamed {
+            before.remove(from);
+            after.remove(to);
+            changes.push(Change::Renamed {
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+    }
+    let shifts = |courses: &BTreeMap<&str, BTreeMap<ClassType, Times>>| {
+        courses
+            .iter()
+            .flat_map(|(c, shifts)| shifts.keys().map(move |k| (c.to_string(), *k)))
+            .collect::<BTreeSet<_>>()
+    };
+    for (course, kind) in shifts(&before).union(&shifts(&after)) {
+        let get = |courses: &BTreeMap<&str, BTreeMap<ClassType, Times>>| {
+            courses
+                .get(course.as_str())
+                .and_then(|s| s.get(kind))
+                .cloned()
+        };
+        let (course, kind) = (course.clone(), *kind);
+        match (get(&before), get(&after)) {
+            (Some(times), None) => changes.push(Change::Removed {
+                course,
+                kind,
+                times,
+            }),
+            (None, Some(times)) => changes.push(Change::Added {
+                course,
+                kind,
+                times,
+            }),
+            (Some(before), Some(after)) if before != after => changes.push(Change::Moved {
+                course,
+                kind,
+                before,
+                after,
+            }),
+            _ => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        diff(&old.parse().unwrap(), &new.parse().unwrap())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn changed_shifts() {
+        let old = "A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nA:L1:14h00:15h00:Tue";
+        let new = "A:T1:10h00:11h00:Mon\nA:T2:12h00:13h00:Mon\nA:L2:14h00:15h00:Tue";
+        assert_eq!(
+            changes(old, new),
+            vec![
+                "~ A T2: Mon 11:00-12:00 -> Mon 12:00-13:00",
+                "- A L1: Tue 14:00-15:00",
+                "+ A L2: Tue 14:00-15:00",
+            ]
+        );
+        assert!(changes(old, old).is_empty());
+    }
+
+    #[test]
+    fn renamed_courses() {
+        let old = "A:T1:10h00:11h00:Mon\nB:T1:11h00:12h00:Mon";
+        assert_eq!(
+            changes(old, "Z:T1:10h00:11h00:Mon\nB:T1:11h00:12h00:Mon"),
+            vec!["= A renamed to Z"]
+        );
+        assert_eq!(
+            changes(old, "Z:T1:09h00:10h00:Mon\nB:T1:11h00:12h00:Mon"),
+            vec!["- A T1: Mon 10:00-11:00", "+ Z T1: Mon 09:00-10:00"]
+        );
+    }
+
+    #[test]
+    fn affected_timetables() {
+        let old = "A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nB:T1:14h00:15h00:Mon"
+            .parse()
+            .unwrap();
+        let new = "A:T1:10h00:11h00:Mon\nA:T2:12h00:13h00:Mon\nC:T1:14h00:15h00:Mon"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&old);
+        let timetables = timetables.iter().collect::<Vec<_>>();
+        let affects = diff(&old, &new)
+            .iter()
+            .map(|c| c.affects(&timetables))
+            .collect::<Vec<_>>();
+        // B renamed to C, used by both, and A T2 moved, used by one.
+        assert_eq!(affects, vec![2, 1]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Reading which shifts someone is already enrolled in.
+//!
+//! An enrolment is either a list of `Course:Shift` lines, which also accepts a
+//! schedule file such as the one written by
+//! [`export::write_schedule`](crate::export::write_schedule) since everything
+//! after the shift is ignored, or a single timetable exported with
+//! [`export::write_json`](crate::export::write_json).
+
+use crate::{error::ErrMessage, timetable::TimetableKey};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct ShiftImport {
+    course: String,
+    shift: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeTableImport {
+    shifts: Vec<ShiftImport>,
+}
+
+/// Either a `timetables` array, which has to have a single entry, or the
+/// `timetable` object older versions exported a single timetable as.
+#[derive(Debug, Deserialize)]
+struct JsonImport {
+    timetable: Option<TimeTableImport>,
+    #[serde(default)]
+    timetables: Vec<TimeTableImport>,
+}
+
+pub fn parse_enrolment(contents: &str) -> Result<TimetableKey, ErrMessage> {
+    if contents.trim_start().starts_with('{') {
+        return parse_json(contents);
+    }
+    let mut key = TimetableKey::new();
+    for (i, l) in contents.lines().enumerate() {
+        if l.trim().is_empty() {
+            continue;
+        }
+        let mut fields = l.split(':').map(str::trim);
+        let (course, kind) = match (fields.next(), fields.next()) {
+            (Some(course), Some(kind)) => (course, kind),
+            _ => return Err(ErrMessage::new("Expected Course:Shift", i + 1, l.into())),
+        };
+        let kind = kind
+            .parse()
+            .map_err(|e| ErrMessage::new(e, i + 1, l.into()))?;
+        key.entry(course.to_string()).or_default().insert(kind);
+    }
+    Ok(key)
+}
+
+fn parse_json(contents: &str) -> Result<TimetableKey, ErrMessage> {
+    let line = |n: usize| {
+        contents
+            .lines()
+            .nth(n.saturating_sub(1))
+            .unwrap_or("")
+            .into()
+    };
+    let import = serde_json::from_str::<JsonImport>(contents)
+        .map_err(|e| ErrMessage::new(e.to_string(), e.line(), line(e.line())))?;
+    let mut timetables = import.timetable.into_iter().chain(import.timetables);
+    let timetable = match (timetables.next(), timetables.next()) {
+        (Some(timetable), None) => timetable,
+        _ => return Err(ErrMessage::new("Expected a single timetable", 1, line(1))),
+    };
+    let mut key = TimetableKey::new();
+    for s in timetable.shifts {
+        let kind = s
+            .shift
+            .parse()
+            .map_err(|e| ErrMessage::new(e, 0, format!("{}:{}", s.course, s.shift)))?;
+        key.entry(s.course).or_default().insert(kind);
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export, ClassType, TimeTable};
+
+    fn key(shifts: &[(&str, ClassType)]) -> TimetableKey {
+        let mut key = TimetableKey::new();
+        for (course, kind) in shifts {
+            key.entry(course.to_string()).or_default().insert(*kind);
+        }
+        key
+    }
+
+    #[test]
+    fn lines() {
+        let enrolment = "PL:T1\nPL:L2:10h00:11h00:Mon\n\nCPD : T3\n";
+        assert_eq!(
+            parse_enrolment(enrolment).unwrap(),
+            key(&[
+                ("PL", ClassType::T(1)),
+                ("PL", ClassType::L(2)),
+                ("CPD", ClassType::T(3)),
+            ])
+        );
+        assert!(parse_enrolment("PL").is_err());
+        assert!(parse_enrolment("PL:X1").is_err());
+    }
+
+    #[test]
+    fn exported_json() {
+        let schedule = "A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nB:L1:14h00:15h00:Mon"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let mut one = Vec::new();
+        export::write_json(&mut one, &timetables[..1]).unwrap();
+        assert_eq!(
+            parse_enrolment(&String::from_utf8(one).unwrap()).unwrap(),
+            timetables[0].key()
+        );
+        let mut both = Vec::new();
+        export::write_json(&mut both, &timetables).unwrap();
+        assert!(parse_enrolment(&String::from_utf8(both).unwrap()).is_err());
+        let old = r#"{ "timetable": { "shifts": [{ "course": "A", "shift": "T1" }] } }"#;
+        assert_eq!(
+            parse_enrolment(old).unwrap(),
+            key(&[("A", ClassType::T(1))])
+        );
+        assert!(parse_enrolment("{}").is_err());
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     fmt::{self, Display},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ErrMessage {
     msg: Cow<'static, str>,
     line_no: usize,
@@ -0,0 +1,431 @@
+//! Machine readable exports of timetables.
+//!
+//! The JSON output has the following shape, with a `timetables` array even
+//! when exporting only one:
+//!
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "timetables": [
+//!     {
+//!       "shifts": [{ "course": "PL", "shift": "L1" }, ...],
+//!       "sessions": [
+//!         { "course": "PL", "shift": "L1", "day": "Mon", "start": "08:00", "end": "10:00" },
+//!         ...
+//!       ],
+//!       "metrics": {
+//!         "days_on_campus": 3,
+//!         "class_minutes": 720,
+//!         "campus_minutes": 840,
+//!         "gap_minutes": 120,
+//!         "earliest_start": "08:00",
+//!         "latest_end": "18:00"
+//!       }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The CSV output has one row per session, with the header
+//! `timetable,course,shift,day,start,end`, where `timetable` is the index of the
+//! timetable in the exported set.
+//!
+//! Any change to either format that isn't purely additive bumps [`SCHEMA_VERSION`].
+//!
+//! [`write_html`] renders a timetable as a standalone HTML page, and
+//! [`write_schedule`] writes a timetable back in the schedule file format,
+//! keeping only the lines of the shifts it uses.
+
+use crate::{
+    shifts::Shifts,
+    timetable::{index_to_time, time_to_index, Metrics, TimeTable},
+    util::{WeekDay, ALL_DAYS},
+};
+use chrono::NaiveTime;
+use serde::Serialize;
+use std::io::{self, Write};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Debug, Serialize)]
+struct ShiftExport<'a> {
+    course: &'a str,
+    shift: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionExport<'a> {
+    course: &'a str,
+    shift: String,
+    day: WeekDay,
+    start: String,
+    end: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MetricsExport {
+    days_on_campus: u32,
+    class_minutes: u32,
+    campus_minutes: u32,
+    gap_minutes: u32,
+    earliest_start: Option<String>,
+    latest_end: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TimeTableExport<'a> {
+    shifts: Vec<ShiftExport<'a>>,
+    sessions: Vec<SessionExport<'a>>,
+    metrics: MetricsExport,
+}
+
+#[derive(Debug, Serialize)]
+struct Set<'a> {
+    schema_version: u32,
+    timetables: Vec<TimeTableExport<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Page<'a> {
+    schema_version: u32,
+    total: usize,
+    page: usize,
+    per_page: usize,
+    timetables: Vec<TimeTableExport<'a>>,
+}
+
+fn format_time(t: NaiveTime) -> String {
+    t.format(TIME_FORMAT).to_string()
+}
+
+impl<'a> From<&'a TimeTable> for TimeTableExport<'a> {
+    fn from(t: &'a TimeTable) -> Self {
+        let Metrics {
+            days_on_campus,
+            class_minutes,
+            campus_minutes,
+            gap_minutes,
+            earliest_start,
+            latest_end,
+        } = t.metrics();
+        Self {
+            shifts: t
+                .shifts()
+                .into_iter()
+                .flat_map(|(course, kinds)| {
+                    kinds.into_iter().map(move |k| ShiftExport {
+                        course,
+                        shift: k.code(),
+                    })
+                })
+                .collect(),
+            sessions: t
+                .sessions()
+                .into_iter()
+                .map(|s| SessionExport {
+                    course: s.course,
+                    shift: s.kind.code(),
+                    day: s.weekday,
+                    start: format_time(s.start),
+                    end: format_time(s.end),
+                })
+                .collect(),
+            metrics: MetricsExport {
+                days_on_campus,
+                class_minutes,
+                campus_minutes,
+                gap_minutes,
+                earliest_start: earliest_start.map(format_time),
+                latest_end: latest_end.map(format_time),
+            },
+        }
+    }
+}
+
+pub fn write_json<'a, W, I>(out: W, timetables: I) -> serde_json::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a TimeTable>,
+{
+    serde_json::to_writer_pretty(
+        out,
+        &Set {
+            schema_version: SCHEMA_VERSION,
+            timetables: timetables.into_iter().map(Into::into).collect(),
+        },
+    )
+}
+
+/// The most timetables a page is allowed to have.
+pub const MAX_PER_PAGE: usize = 100;
+
+/// Like [`write_json`], but only for the timetables in page `page` of
+/// `timetables`, which also records the total number of timetables so a client
+/// can tell how many pages there are. Callers should keep `per_page` between 1
+/// and [`MAX_PER_PAGE`]; pages past the end are empty.
+pub fn write_json_page<W: Write>(
+    out: W,
+    timetables: &[&TimeTable],
+    page: usize,
+    per_page: usize,
+) -> serde_json::Result<()> {
+    serde_json::to_writer_pretty(
+        out,
+        &Page {
+            schema_version: SCHEMA_VERSION,
+            total: timetables.len(),
+            page,
+            per_page,
+            timetables: timetables
+                .iter()
+                .skip(page.saturating_mul(per_page))
+                .take(per_page)
+                .map(|t| TimeTableExport::from(*t))
+                .collect(),
+        },
+    )
+}
+
+pub fn write_csv<'a, W, I>(mut out: W, timetables: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a TimeTable>,
+{
+    writeln!(out, "timetable,course,shift,day,start,end")?;
+    for (i, t) in timetables.into_iter().enumerate() {
+        for s in t.sessions() {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                i,
+                csv_field(s.course),
+                s.kind.code(),
+                s.weekday,
+                format_time(s.start),
+                format_time(s.end)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the lines of `schedule` that belong to the shifts chosen in `timetable`,
+/// unchanged and in their original order, so the result can be parsed again as a
+/// schedule with a single possible timetable.
+pub fn write_schedule<W: Write>(
+    mut out: W,
+    schedule: &Shifts,
+    timetable: &TimeTable,
+) -> io::Result<()> {
+    let shifts = timetable.shifts();
+    schedule
+        .classes()
+        .iter()
+        .filter(|c| {
+            shifts
+                .get(c.name.as_str())
+                .is_some_and(|k| k.contains(&c.kind))
+        })
+        .try_for_each(|c| writeln!(out, "{}", c.line))
+}
+
+const HTML_COLORS: [&str; 6] = [
+    "#e06c75", "#98c379", "#e5c07b", "#61afef", "#c678dd", "#56b6c2",
+];
+
+/// Writes `timetable` as a standalone HTML page with a table that has one
+/// column per day and one row per half hour. Weekends are only shown when
+/// they have classes.
+pub fn write_html<W: Write>(mut out: W, timetable: &TimeTable) -> io::Result<()> {
+    let sessions = timetable.sessions();
+    let courses = timetable.shifts().into_keys().collect::<Vec<_>>();
+    let days = ALL_DAYS
+        .iter()
+        .filter(|d| **d < WeekDay::Sat || !timetable.free_day(**d))
+        .collect::<Vec<_>>();
+    let first = sessions.iter().map(|s| time_to_index(s.start)).min();
+    let last = sessions.iter().map(|s| time_to_index(s.end)).max();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        "<html><head><meta charset=\"utf-8\"><title>Timetable</title>"
+    )?;
+    writeln!(
+        out,
+        "<style>table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:2px 6px}}</style>"
+    )?;
+    writeln!(out, "</head><body><table>")?;
+    write!(out, "<tr><th></th>")?;
+    for d in &days {
+        write!(out, "<th>{}</th>", d)?;
+    }
+    writeln!(out, "</tr>")?;
+    if let (Some(first), Some(last)) = (first, last) {
+        for i in first..last {
+            write!(out, "<tr><th>{}</th>", format_time(index_to_time(i)))?;
+            for d in &days {
+                let covering = sessions.iter().find(|s| {
+                    s.weekday == **d && time_to_index(s.start) <= i && i < time_to_index(s.end)
+                });
+                match covering {
+                    Some(s) if time_to_index(s.start) == i => {
+                        let color = courses
+                            .iter()
+                            .position(|c| *c == s.course)
+                            .map_or(HTML_COLORS[0], |n| HTML_COLORS[n % HTML_COLORS.len()]);
+                        write!(
+                            out,
+                            "<td rowspan=\"{}\" style=\"background:{}\">{} {}</td>",
+                            time_to_index(s.end) - i,
+                            color,
+                            s.kind.code(),
+                            html_escape(s.course)
+                        )?
+                    }
+                    Some(_) => {}
+                    None => write!(out, "<td></td>")?,
+                }
+            }
+            writeln!(out, "</tr>")?;
+        }
+    }
+    writeln!(out, "</table></body></html>")
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::ClassType;
+
+    fn timetables(schedule: &str) -> Vec<TimeTable> {
+        let schedule = Shifts::parse_schedule(schedule.lines().map(String::from)).unwrap();
+        TimeTable::all_the_combos(&schedule)
+    }
+
+    #[test]
+    fn json() {
+        let timetables = timetables("A:T1:10h00:11h00:Mon\nA:L1:14h00:15h30:Mon");
+        let mut out = Vec::new();
+        write_json(&mut out, &timetables).unwrap();
+        let json = serde_json::from_slice::<serde_json::Value>(&out).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "timetables": [{
+                    "shifts": [
+                        { "course": "A", "shift": "T1" },
+                        { "course": "A", "shift": "L1" }
+                    ],
+                    "sessions": [
+                        { "course": "A", "shift": "T1", "day": "Mon", "start": "10:00", "end": "11:00" },
+                        { "course": "A", "shift": "L1", "day": "Mon", "start": "14:00", "end": "15:30" }
+                    ],
+                    "metrics": {
+                        "days_on_campus": 1,
+                        "class_minutes": 150,
+                        "campus_minutes": 330,
+                        "gap_minutes": 180,
+                        "earliest_start": "10:00",
+                        "latest_end": "15:30"
+                    }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let timetables =
+            timetables("A, B:T1:10h00:11h00:Mon\nA, B:L1:14h00:15h30:Tue\nC:T1:09h00:10h00:Mon");
+        let mut out = Vec::new();
+        write_csv(&mut out, &timetables).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timetable,course,shift,day,start,end\n\
+             0,C,T1,Mon,09:00,10:00\n\
+             0,\"A, B\",T1,Mon,10:00,11:00\n\
+             0,\"A, B\",L1,Tue,14:00,15:30\n"
+        );
+    }
+
+    #[test]
+    fn chosen_shifts() {
+        let lines = "A:T1:10h00:11h00:Mon:Smith\n\
+                     \n\
+                     B:T1:09h00:10h00:Tue\n\
+                     A:T2:11h00:12h00:Mon\n\
+                     A:T1 : 14h00:15h00:Wed";
+        let schedule = Shifts::parse_schedule(lines.lines().map(String::from)).unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let chosen = timetables
+            .iter()
+            .find(|t| t.has_the_shift(ClassType::T(1), "A"))
+            .unwrap();
+        let mut out = Vec::new();
+        write_schedule(&mut out, &schedule, chosen).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "A:T1:10h00:11h00:Mon:Smith\nB:T1:09h00:10h00:Tue\nA:T1 : 14h00:15h00:Wed\n"
+        );
+        let again = Shifts::parse_schedule(out.lines().map(String::from)).unwrap();
+        assert_eq!(TimeTable::all_the_combos(&again).len(), 1);
+    }
+
+    #[test]
+    fn html() {
+        let timetables = timetables("A<B:T1:10h00:11h00:Mon");
+        let mut out = Vec::new();
+        write_html(&mut out, &timetables[0]).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("<tr><th></th><th>Mon</th><th>Tue</th>"));
+        assert!(html.contains(
+            "<tr><th>10:00</th><td rowspan=\"2\" style=\"background:#e06c75\">T1 A&lt;B</td>"
+        ));
+        assert!(html.contains("<tr><th>10:30</th><td></td>"));
+        assert!(!html.contains("<th>11:00</th>"));
+    }
+
+    fn page(timetables: &[TimeTable], page: usize, per_page: usize) -> serde_json::Value {
+        let mut out = Vec::new();
+        let timetables = timetables.iter().collect::<Vec<_>>();
+        write_json_page(&mut out, &timetables, page, per_page).unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[test]
+    fn pages() {
+        let timetables =
+            timetables("A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nA:T3:12h00:13h00:Mon");
+        let first = page(&timetables, 0, 2);
+        assert_eq!(first["total"], 3);
+        assert_eq!(first["timetables"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            page(&timetables, 1, 2)["timetables"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        let past_the_end = page(&timetables, usize::MAX, MAX_PER_PAGE);
+        assert!(past_the_end["timetables"].as_array().unwrap().is_empty());
+    }
+}
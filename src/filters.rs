@@ -0,0 +1,113 @@
+use crate::{
+    timetable::TimeTable,
+    util::{ClassType, WeekDay},
+};
+use chrono::NaiveTime;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A set of constraints a timetable has to satisfy. Every field that is left
+/// empty doesn't constrain anything.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimetableFilters {
+    pub starts_after: Option<NaiveTime>,
+    pub ends_before: Option<NaiveTime>,
+    pub free_days: BTreeSet<WeekDay>,
+    pub has_the_shift: Vec<(ClassType, String)>,
+    pub hasnt_the_shift: Vec<(ClassType, String)>,
+}
+
+impl TimetableFilters {
+    pub fn filter(&self, timetable: &TimeTable) -> bool {
+        self.starts_after.is_none_or(|t| timetable.starts_after(t))
+            && self.ends_before.is_none_or(|t| timetable.ends_before(t))
+            && self.free_days.iter().all(|d| timetable.free_day(*d))
+            && self
+                .has_the_shift
+                .iter()
+                .all(|(s, t)| timetable.has_the_shift(*s, t))
+            && self
+                .hasnt_the_shift
+                .iter()
+                .all(|(s, t)| timetable.hasnt_the_shift(*s, t))
+    }
+
+    /// The timetables that pass this filter, in the same order.
+    pub fn apply<'a>(&self, timetables: &'a [TimeTable]) -> Vec<&'a TimeTable> {
+        #[cfg(feature = "parallel")]
+        let iter = timetables.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = timetables.iter();
+        iter.filter(|t| self.filter(t)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timetables() -> Vec<TimeTable> {
+        let schedule = "A:T1:08h00:10h00:Mon\n\
+                        A:T2:14h00:16h00:Fri\n\
+                        B:T1:10h00:12h00:Tue\n\
+                        B:T2:18h00:20h00:Tue"
+            .parse()
+            .unwrap();
+        TimeTable::all_the_combos(&schedule)
+    }
+
+    fn count(filters: &TimetableFilters) -> usize {
+        filters.apply(&timetables()).len()
+    }
+
+    #[test]
+    fn each_filter() {
+        let time = |h| Some(NaiveTime::from_hms(h, 0, 0));
+        let none = TimetableFilters::default();
+        assert_eq!(count(&none), 4);
+        let starts_after = TimetableFilters {
+            starts_after: time(9),
+            ..none.clone()
+        };
+        assert_eq!(count(&starts_after), 2);
+        let ends_before = TimetableFilters {
+            ends_before: time(18),
+            ..none.clone()
+        };
+        assert_eq!(count(&ends_before), 2);
+        let both = TimetableFilters {
+            ends_before: time(18),
+            ..starts_after
+        };
+        assert_eq!(count(&both), 1);
+        let free_day = TimetableFilters {
+            free_days: vec![WeekDay::Fri].into_iter().collect(),
+            ..none.clone()
+        };
+        assert_eq!(count(&free_day), 2);
+        let has = TimetableFilters {
+            has_the_shift: vec![(ClassType::T(2), "B".into())],
+            ..none.clone()
+        };
+        assert_eq!(count(&has), 2);
+        let hasnt = TimetableFilters {
+            hasnt_the_shift: vec![(ClassType::T(1), "A".into())],
+            ..none
+        };
+        assert_eq!(count(&hasnt), 2);
+    }
+
+    #[test]
+    fn saved_filters() {
+        let mut filters = TimetableFilters::default();
+        filters.free_days.insert(WeekDay::Fri);
+        filters.has_the_shift.push((ClassType::L(2), "PL".into()));
+        let json = serde_json::to_string(&filters).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TimetableFilters>(&json).unwrap(),
+            filters
+        );
+    }
+}
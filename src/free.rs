@@ -0,0 +1,268 @@
+//! Finding the time in a week when everyone is free.
+//!
+//! What someone is busy with is kept like a timetable's occupancy, one bitmask
+//! of half hours per day, so several people's can be merged with a bitwise or.
+//! It can come from schedule files, such as those written by
+//! [`export::write_schedule`](crate::export::write_schedule), or from iCalendar
+//! files, where every event is taken to happen every week and is rounded out to
+//! whole half hours.
+
+use crate::{
+    render::{Cell, Grid},
+    shifts::Shifts,
+    theme::CourseStyle,
+    timetable::{index_to_time, slot_mask, time_to_index, SLOTS},
+    util::{WeekDay, ALL_DAYS, WEEKDAYS},
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::collections::BTreeSet;
+
+/// Half hours that are taken, one bit per half hour of each day.
+pub type Busy = [u64; WEEKDAYS];
+
+/// Every class in `schedule`, whichever shift it belongs to.
+pub fn busy_from_schedule(schedule: &Shifts) -> Busy {
+    let mut busy = [0; WEEKDAYS];
+    for c in schedule.classes() {
+        busy[c.weekday as usize] |= slot_mask(c.start, c.end);
+    }
+    busy
+}
+
+/// Every event in an iCalendar file, on its day of the week. Events that go
+/// past midnight take the rest of the day.
+pub fn busy_from_ical(calendar: &str) -> Result<Busy, String> {
+    let mut busy = [0; WEEKDAYS];
+    let mut event: Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> = None;
+    // Long lines are folded by starting the next one with a space.
+    let unfolded = calendar.replace("\r\n ", "").replace("\n ", "");
+    for line in unfolded.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.split(';').next().unwrap_or(name), value.trim()),
+            None => continue,
+        };
+        match (name, &mut event) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some((None, None)),
+            ("DTSTART", Some(e)) => e.0 = Some(parse_ical_time(value)?),
+            ("DTEND", Some(e)) => e.1 = Some(parse_ical_time(value)?),
+            ("END", Some((start, end))) if value == "VEVENT" => {
+                let start = start.ok_or("Event without a start")?;
+                let end = end.unwrap_or(start);
+                let day = ALL_DAYS[start.weekday().num_days_from_monday() as usize];
+                let first = time_to_index(start.time());
+                let last = match end.date() > start.date() {
+                    true => SLOTS,
+                    false => {
+                        let t = end.time();
+                        time_to_index(t) + usize::from(t.minute() % 30 != 0 || t.second() != 0)
+                    }
+                };
+                let mask = match last > first {
+                    true => (u64::MAX >> (64 - (last - first))) << first,
+                    false => 0,
+                };
+                busy[day as usize] |= mask;
+                event = None;
+            }
+            _ => {}
+        }
+    }
+    Ok(busy)
+}
+
+/// `20200210T090000Z`, `20200210T090000` or `20200210`, taking the time as it
+/// is written whatever the time zone.
+fn parse_ical_time(value: &str) -> Result<NaiveDateTime, String> {
+    let value = value.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d").map(|d| d.and_hms(0, 0, 0)))
+        .map_err(|_| format!("Invalid date: {}", value))
+}
+
+/// A stretch of time in a week when everyone is free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeWindow {
+    pub weekday: WeekDay,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl FreeWindow {
+    pub fn minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+}
+
+/// Which free windows are wanted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeTimeQuery {
+    pub days: BTreeSet<WeekDay>,
+    pub from: NaiveTime,
+    pub until: NaiveTime,
+    pub min_minutes: i64,
+}
+
+impl Default for FreeTimeQuery {
+    /// Weekdays from 08h00 to 20h00, at least half an hour long.
+    fn default() -> Self {
+        Self {
+            days: ALL_DAYS[..5].iter().copied().collect(),
+            from: NaiveTime::from_hms(8, 0, 0),
+            until: NaiveTime::from_hms(20, 0, 0),
+            min_minutes: 30,
+        }
+    }
+}
+
+/// Merges what each of `people` is busy with.
+pub fn everyone<'a, I: IntoIterator<Item = &'a Busy>>(people: I) -> Busy {
+    people.into_iter().fold([0; WEEKDAYS], |mut acc, busy| {
+        acc.iter_mut().zip(busy).for_each(|(a, b)| *a |= b);
+        acc
+    })
+}
+
+/// The windows in `query` where nothing in `busy` happens, by day and start.
+pub fn free_windows(busy: &Busy, query: &FreeTimeQuery) -> Vec<FreeWindow> {
+    let (from, until) = (time_to_index(query.from), time_to_index(query.until));
+    let mut windows = Vec::new();
+    for day in &query.days {
+        let taken = busy[*day as usize];
+        let mut start = None;
+        for i in from..=until {
+            let free = i < until && taken >> i & 1 == 0;
+            match (free, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    windows.push(FreeWindow {
+                        weekday: *day,
+                        start: index_to_time(s),
+                        end: index_to_time(i),
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    windows.retain(|w| w.minutes() >= query.min_minutes);
+    windows
+}
+
+/// The free windows drawn in `free`, next to what's busy drawn in `busy`.
+pub fn free_grid(
+    busy_slots: &Busy,
+    windows: &[FreeWindow],
+    free: CourseStyle,
+    busy: CourseStyle,
+) -> Grid {
+    let mut grid = Grid::default();
+    let cell = |text: &str, style| Cell {
+        text: text.to_string(),
+        style,
+        theory: true,
+    };
+    for w in windows {
+        for i in time_to_index(w.start)..time_to_index(w.end) {
+            grid.set(w.weekday, i, cell("free", free));
+        }
+    }
+    let days = windows.iter().map(|w| w.weekday).collect::<BTreeSet<_>>();
+    let rows = windows
+        .iter()
+        .map(|w| time_to_index(w.start))
+        .min()
+        .zip(windows.iter().map(|w| time_to_index(w.end)).max());
+    if let Some((first, last)) = rows {
+        for day in days {
+            for i in (first..last).filter(|i| busy_slots[day as usize] >> i & 1 == 1) {
+                grid.set(day, i, cell("busy", busy));
+            }
+        }
+    }
+    grid.add_legend("free", free);
+    grid.add_legend("busy", busy);
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hour(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms(h, m, 0)
+    }
+
+    #[test]
+    fn calendars() {
+        let calendar = "BEGIN:VCALENDAR\r\n\
+                        BEGIN:VEVENT\r\n\
+                        SUMMARY:Meeting\r\n\
+                        DTSTART;TZID=Europe/Lisbon:20200211T0\r\n 90000\r\n\
+                        DTEND:20200211T101500Z\r\n\
+                        END:VEVENT\r\n\
+                        BEGIN:VEVENT\r\n\
+                        DTSTART:20200214T220000\r\n\
+                        DTEND:20200215T010000\r\n\
+                        END:VEVENT\r\n\
+                        END:VCALENDAR\r\n";
+        let busy = busy_from_ical(calendar).unwrap();
+        // Ends at 10h15, so the half hour from 10h00 is taken too.
+        assert_eq!(
+            busy[WeekDay::Tue as usize],
+            slot_mask(hour(9, 0), hour(10, 30))
+        );
+        // Goes past midnight, so it takes the rest of the day.
+        assert_eq!(busy[WeekDay::Fri as usize], 0b1111 << 44);
+        assert_eq!(busy[WeekDay::Sat as usize], 0);
+        assert_eq!(
+            busy_from_ical("BEGIN:VEVENT\nDTSTART:tomorrow\nEND:VEVENT"),
+            Err("Invalid date: tomorrow".to_string())
+        );
+        assert!(busy_from_ical("BEGIN:VEVENT\nEND:VEVENT").is_err());
+    }
+
+    #[test]
+    fn windows() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:14h00:15h00:Mon"
+            .parse()
+            .unwrap();
+        let mut other = [0; WEEKDAYS];
+        other[WeekDay::Mon as usize] = slot_mask(hour(11, 0), hour(11, 30));
+        let busy = everyone(&[busy_from_schedule(&schedule), other]);
+        let query = FreeTimeQuery {
+            days: BTreeSet::from([WeekDay::Mon]),
+            min_minutes: 150,
+            ..FreeTimeQuery::default()
+        };
+        let window = |start, end| FreeWindow {
+            weekday: WeekDay::Mon,
+            start,
+            end,
+        };
+        let windows = free_windows(&busy, &query);
+        assert_eq!(
+            windows,
+            vec![
+                window(hour(11, 30), hour(14, 0)),
+                window(hour(15, 0), hour(20, 0))
+            ]
+        );
+        assert_eq!(windows[1].minutes(), 300);
+        let all = free_windows(&busy, &FreeTimeQuery::default());
+        assert_eq!(all.len(), 3 + 4);
+        assert_eq!(all[0], window(hour(8, 0), hour(10, 0)));
+
+        let style = CourseStyle {
+            color: None,
+            pattern: None,
+            second_pattern: None,
+        };
+        let grid = free_grid(&busy, &windows, style, style);
+        assert_eq!(grid.get(WeekDay::Mon, 23).unwrap().text, "free");
+        assert_eq!(grid.get(WeekDay::Mon, 28).unwrap().text, "busy");
+        // Only the hours around the windows are drawn.
+        assert_eq!(grid.get(WeekDay::Mon, 20), None);
+    }
+}
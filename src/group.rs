@@ -0,0 +1,252 @@
+//! Picking a timetable for each student of a group at once, so that they share
+//! as many shifts of the courses they have in common, and as much free time, as
+//! possible.
+//!
+//! When there are few enough combinations every one of them is scored, with
+//! more the search starts from each student's best timetables and keeps
+//! swapping one student's timetable for the one that scores best with the
+//! others', until no swap helps.
+
+use crate::{
+    timetable::{slot_mask, TimeTable},
+    util::{ClassType, WeekDay},
+};
+use chrono::NaiveTime;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Up to how many combinations are all tried.
+const EXHAUSTIVE_LIMIT: usize = 1_000_000;
+
+/// How many starting points the search tries when there are more.
+const RESTARTS: usize = 20;
+
+/// Days where free time counts, free days included.
+const FREE_DAYS: [WeekDay; 5] = [
+    WeekDay::Mon,
+    WeekDay::Tue,
+    WeekDay::Wed,
+    WeekDay::Thu,
+    WeekDay::Fri,
+];
+
+/// How much each thing the group has in common is worth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupWeights {
+    /// For every student beyond the first that picks the same shift.
+    pub shifts: u32,
+    /// For every half hour from 08h00 to 20h00 on weekdays where everyone is
+    /// free.
+    pub free_time: u32,
+}
+
+impl Default for GroupWeights {
+    /// A shared shift is worth as much as two hours everyone has free.
+    fn default() -> Self {
+        Self {
+            shifts: 4,
+            free_time: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupChoice<'a> {
+    /// One timetable per student, in the order they were given.
+    pub timetables: Vec<&'a TimeTable>,
+    /// The shifts picked by more than one student, with who picked them.
+    pub shared_shifts: BTreeMap<(&'a str, ClassType), Vec<usize>>,
+    /// Time from 08h00 to 20h00 on weekdays where everyone is free.
+    pub shared_free_minutes: u32,
+    pub score: u32,
+}
+
+impl<'a> GroupChoice<'a> {
+    fn new(timetables: Vec<&'a TimeTable>, weights: GroupWeights) -> Self {
+        let mut picked = BTreeMap::<_, Vec<usize>>::new();
+        for (i, t) in timetables.iter().enumerate() {
+            for s in t.chosen() {
+                picked
+                    .entry((s.course.as_str(), s.kind))
+                    .or_default()
+                    .push(i);
+            }
+        }
+        picked.retain(|_, who| who.len() > 1);
+        let (shared, free) = (shared_shifts(&timetables), free_slots(&timetables));
+        Self {
+            timetables,
+            shared_shifts: picked,
+            shared_free_minutes: free * 30,
+            score: weights.shifts * shared + weights.free_time * free,
+        }
+    }
+}
+
+/// The best `n` ways of picking one timetable from each of `candidates`, which
+/// hold the timetables each student would take, best first.
+pub fn best_choices<'a>(
+    candidates: &[Vec<&'a TimeTable>],
+    weights: GroupWeights,
+    n: usize,
+) -> Vec<GroupChoice<'a>> {
+    if candidates.iter().any(Vec::is_empty) {
+        return Vec::new();
+    }
+    let total = candidates
+        .iter()
+        .try_fold(1_usize, |acc, c| acc.checked_mul(c.len()));
+    let picks = match total {
+        Some(total) if total <= EXHAUSTIVE_LIMIT => exhaustive(candidates, weights, total, n),
+        _ => local_search(candidates, weights, n),
+    };
+    picks
+        .into_iter()
+        .map(|pick| {
+            let timetables = pick.iter().zip(candidates).map(|(i, c)| c[*i]).collect();
+            GroupChoice::new(timetables, weights)
+        })
+        .collect()
+}
+
+/// Scores every combination, where the `i`th one picks the timetables at the
+/// digits of `i` written in a mixed radix of the number of candidates.
+fn exhaustive(
+    candidates: &[Vec<&TimeTable>],
+    weights: GroupWeights,
+    total: usize,
+    n: usize,
+) -> Vec<Vec<usize>> {
+    let decode = |mut i: usize| {
+        candidates
+            .iter()
+            .map(|c| {
+                let digit = i % c.len();
+                i /= c.len();
+                digit
+            })
+            .collect::<Vec<_>>()
+    };
+    let score = |i: usize| (score(candidates, &decode(i), weights), i);
+    #[cfg(feature = "parallel")]
+    let mut scored = (0..total).into_par_iter().map(score).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let mut scored = (0..total).map(score).collect::<Vec<_>>();
+    scored.sort_by(|(a, ia), (b, ib)| b.cmp(a).then(ia.cmp(ib)));
+    scored.into_iter().take(n).map(|(_, i)| decode(i)).collect()
+}
+
+fn local_search(
+    candidates: &[Vec<&TimeTable>],
+    weights: GroupWeights,
+    n: usize,
+) -> Vec<Vec<usize>> {
+    let climb = |start: usize| {
+        let mut pick = candidates
+            .iter()
+            .map(|c| start.min(c.len() - 1))
+            .collect::<Vec<_>>();
+        let mut best = score(candidates, &pick, weights);
+        loop {
+            let mut improved = false;
+            for student in 0..candidates.len() {
+                for i in 0..candidates[student].len() {
+                    let old = std::mem::replace(&mut pick[student], i);
+                    let s = score(candidates, &pick, weights);
+                    if s > best {
+                        best = s;
+                        improved = true;
+                    } else {
+                        pick[student] = old;
+                    }
+                }
+            }
+            if !improved {
+                break (best, pick);
+            }
+        }
+    };
+    #[cfg(feature = "parallel")]
+    let mut found = (0..RESTARTS).into_par_iter().map(climb).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let mut found = (0..RESTARTS).map(climb).collect::<Vec<_>>();
+    found.sort_by(|(a, pa), (b, pb)| b.cmp(a).then_with(|| pa.cmp(pb)));
+    found.dedup();
+    found.into_iter().take(n).map(|(_, pick)| pick).collect()
+}
+
+fn score(candidates: &[Vec<&TimeTable>], pick: &[usize], weights: GroupWeights) -> u32 {
+    let timetables = pick
+        .iter()
+        .zip(candidates)
+        .map(|(i, c)| c[*i])
+        .collect::<Vec<_>>();
+    weights.shifts * shared_shifts(&timetables) + weights.free_time * free_slots(&timetables)
+}
+
+/// For every shift, how many students beyond the first picked it.
+fn shared_shifts(timetables: &[&TimeTable]) -> u32 {
+    let mut seen = BTreeSet::new();
+    let mut shared = 0;
+    for s in timetables.iter().flat_map(|t| t.chosen()) {
+        if !seen.insert((s.course.as_str(), s.kind)) {
+            shared += 1;
+        }
+    }
+    shared
+}
+
+/// The half hours from 08h00 to 20h00 on weekdays where no one has classes.
+fn free_slots(timetables: &[&TimeTable]) -> u32 {
+    let window = slot_mask(NaiveTime::from_hms(8, 0, 0), NaiveTime::from_hms(20, 0, 0));
+    FREE_DAYS
+        .iter()
+        .map(|d| {
+            let busy = timetables
+                .iter()
+                .fold(0, |acc, t| acc | t.occupancy()[*d as usize]);
+            (window & !busy).count_ones()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_shifts_are_best() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let each = timetables.iter().collect::<Vec<_>>();
+        let candidates = vec![each.clone(), each];
+        let choices = best_choices(&candidates, GroupWeights::default(), 4);
+        let scores = choices.iter().map(|c| c.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![122, 122, 116, 116]);
+        let best = &choices[0];
+        let keys = best.timetables.iter().map(|t| t.key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec![timetables[0].key(), timetables[0].key()]);
+        assert_eq!(
+            best.shared_shifts,
+            BTreeMap::from([(("A", ClassType::T(1)), vec![0, 1])])
+        );
+        assert_eq!(best.shared_free_minutes, 118 * 30);
+        assert!(choices[2].shared_shifts.is_empty());
+
+        // The search finds the same when there are too many to try them all.
+        let picks = local_search(&candidates, GroupWeights::default(), 2);
+        assert_eq!(picks, vec![vec![0, 0], vec![1, 1]]);
+    }
+
+    #[test]
+    fn no_timetables_no_choices() {
+        let schedule = "A:T1:10h00:11h00:Mon".parse().unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let candidates = vec![timetables.iter().collect(), Vec::new()];
+        assert!(best_choices(&candidates, GroupWeights::default(), 1).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! Narrowing down timetables by answering questions, twenty questions style.
+//!
+//! Every question maps its answers to changes of [`TimetableFilters`], and the
+//! one proposed next is the one whose answers split the remaining timetables
+//! the most evenly, so that any answer rules out as many as possible.
+
+use crate::{
+    filters::TimetableFilters,
+    timetable::TimeTable,
+    util::{ClassType, WeekDay, ALL_DAYS},
+};
+use chrono::NaiveTime;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+
+/// Hours asked about for [`Question::StartsAfter`].
+const START_HOURS: [u32; 6] = [9, 10, 11, 12, 13, 14];
+/// Hours asked about for [`Question::EndsBefore`].
+const END_HOURS: [u32; 8] = [12, 13, 14, 15, 16, 17, 18, 19];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Question {
+    /// Whether this day has to be free.
+    FreeDay(WeekDay),
+    /// Whether classes have to start after this time.
+    StartsAfter(NaiveTime),
+    /// Whether classes have to end before this time.
+    EndsBefore(NaiveTime),
+    /// Which of these shifts of a course to pick.
+    Shift(String, Vec<ClassType>),
+}
+
+impl Question {
+    /// The answers that narrow down the timetables. Not caring is always an
+    /// answer too, and leaves the filters as they are.
+    pub fn options(&self) -> Vec<String> {
+        match self {
+            Self::FreeDay(_) | Self::StartsAfter(_) | Self::EndsBefore(_) => vec!["Yes".into()],
+            Self::Shift(_, kinds) => kinds.iter().map(|k| k.code()).collect(),
+        }
+    }
+
+    /// How many of `timetables` are left after each of the [`Question::options`].
+    pub fn counts(&self, timetables: &[&TimeTable]) -> Vec<usize> {
+        let count = |f: &(dyn Fn(&TimeTable) -> bool + Sync)| {
+            #[cfg(feature = "parallel")]
+            let iter = timetables.par_iter();
+            #[cfg(not(feature = "parallel"))]
+            let iter = timetables.iter();
+            iter.filter(|t| f(t)).count()
+        };
+        match self {
+            Self::FreeDay(d) => vec![count(&|t| t.free_day(*d))],
+            Self::StartsAfter(time) => vec![count(&|t| t.starts_after(*time))],
+            Self::EndsBefore(time) => vec![count(&|t| t.ends_before(*time))],
+            Self::Shift(course, kinds) => kinds
+                .iter()
+                .map(|k| count(&|t| t.has_the_shift(*k, course)))
+                .collect(),
+        }
+    }
+
+    /// Changes `filters` to keep only the timetables that agree with the
+    /// `option`th answer.
+    pub fn answer(&self, option: usize, filters: &mut TimetableFilters) {
+        match self {
+            Self::FreeDay(d) => {
+                filters.free_days.insert(*d);
+            }
+            Self::StartsAfter(t) => filters.starts_after = Some(*t),
+            Self::EndsBefore(t) => filters.ends_before = Some(*t),
+            Self::Shift(course, kinds) => {
+                if let Some(k) = kinds.get(option) {
+                    filters.has_the_shift.push((*k, course.clone()));
+                }
+            }
+        }
+    }
+}
+
+impl Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FreeDay(d) => write!(f, "Free {}?", d),
+            Self::StartsAfter(t) => write!(f, "Start after {}?", t.format("%H:%M")),
+            Self::EndsBefore(t) => write!(f, "End before {}?", t.format("%H:%M")),
+            Self::Shift(course, kinds) => {
+                let kinds = kinds.iter().map(|k| k.code()).collect::<Vec<_>>();
+                write!(f, "{} {}?", course, kinds.join(" or "))
+            }
+        }
+    }
+}
+
+/// The question that best splits `timetables`, with how many are left after
+/// each of its answers, leaving out those in `skip`. `None` when every
+/// question left would keep all of them or none of them.
+pub fn next_question(
+    timetables: &[&TimeTable],
+    skip: &[Question],
+) -> Option<(Question, Vec<usize>)> {
+    let total = timetables.len();
+    candidates(timetables)
+        .into_iter()
+        .filter(|q| !skip.contains(q))
+        .map(|q| {
+            let counts = q.counts(timetables);
+            (q, counts)
+        })
+        .filter(|(_, counts)| counts.iter().all(|n| *n < total))
+        .filter(|(_, counts)| counts.iter().any(|n| *n > 0))
+        .map(|(q, counts)| (entropy(&counts, total), q, counts))
+        .fold(
+            None,
+            |best: Option<(f64, Question, Vec<usize>)>, c| match best {
+                Some(b) if b.0 >= c.0 => Some(b),
+                _ => Some(c),
+            },
+        )
+        .map(|(_, q, counts)| (q, counts))
+}
+
+/// Every question that could be asked about `timetables`.
+fn candidates(timetables: &[&TimeTable]) -> Vec<Question> {
+    let hour = |h| NaiveTime::from_hms(h, 0, 0);
+    let mut shifts = BTreeMap::<(&str, bool), BTreeSet<ClassType>>::new();
+    for t in timetables {
+        for s in t.chosen() {
+            shifts
+                .entry((s.course.as_str(), s.kind.unique_id() > 0))
+                .or_default()
+                .insert(s.kind);
+        }
+    }
+    ALL_DAYS
+        .iter()
+        .map(|d| Question::FreeDay(*d))
+        .chain(START_HOURS.iter().map(|h| Question::StartsAfter(hour(*h))))
+        .chain(END_HOURS.iter().map(|h| Question::EndsBefore(hour(*h))))
+        .chain(shifts.into_iter().filter(|(_, kinds)| kinds.len() > 1).map(
+            |((course, _), kinds)| Question::Shift(course.to_string(), kinds.into_iter().collect()),
+        ))
+        .collect()
+}
+
+/// How evenly the answers split `total` timetables, counting the ones no
+/// answer keeps as one more answer.
+fn entropy(counts: &[usize], total: usize) -> f64 {
+    let rest = total.saturating_sub(counts.iter().sum());
+    counts
+        .iter()
+        .chain(Some(&rest))
+        .filter(|n| **n > 0)
+        .map(|n| *n as f64 / total as f64)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn questions() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let all = timetables.iter().collect::<Vec<_>>();
+        let free_monday = Question::FreeDay(WeekDay::Mon);
+        let shift = Question::Shift("A".to_string(), vec![ClassType::T(1), ClassType::T(2)]);
+        assert_eq!(
+            next_question(&all, &[]),
+            Some((free_monday.clone(), vec![1]))
+        );
+        let skip = [free_monday.clone(), Question::FreeDay(WeekDay::Tue)];
+        assert_eq!(
+            next_question(&all, &skip),
+            Some((shift.clone(), vec![1, 1]))
+        );
+        assert_eq!(shift.to_string(), "A T1 or T2?");
+        assert_eq!(shift.options(), vec!["T1", "T2"]);
+
+        let mut filters = TimetableFilters::default();
+        shift.answer(1, &mut filters);
+        assert_eq!(
+            filters.has_the_shift,
+            vec![(ClassType::T(2), "A".to_string())]
+        );
+        let left = filters.apply(&timetables);
+        assert_eq!(left.len(), 1);
+        // Nothing is left to tell apart.
+        assert_eq!(next_question(&left, &[]), None);
+    }
+
+    #[test]
+    fn even_splits_are_better() {
+        assert_eq!(entropy(&[1, 1], 2), 1.0);
+        assert_eq!(entropy(&[2], 4), 1.0);
+        assert_eq!(entropy(&[4], 4), 0.0);
+        assert!(entropy(&[1], 4) < entropy(&[2], 4));
+    }
+}
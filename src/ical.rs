@@ -1,8 +1,11 @@
-use crate::timetable::{index_to_time, TimeTable};
+use crate::{
+    free::FreeWindow,
+    timetable::TimeTable,
+    util::{WeekDay, ALL_DAYS},
+};
 use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 use icalendar::{Calendar, Component, Event};
 use std::{
-    collections::HashMap,
     io::{self, Write},
     iter::successors,
 };
@@ -24,7 +27,7 @@ impl<'a> EventBuilder<'a> {
     }
 
     fn starts(mut self, t: NaiveTime) -> Self {
-        if self.start != None {
+        if self.start.is_some() {
             panic!("Can't start twice");
         }
         self.start = Some(t);
@@ -43,47 +46,89 @@ pub fn write_cal<W: Write>(
     time: &TimeTable,
     start_day: NaiveDate,
     end_day: NaiveDate,
+) -> io::Result<()> {
+    let events = time
+        .sessions()
+        .into_iter()
+        .map(|s| (s.weekday, s.course, s.start, s.end))
+        .collect::<Vec<_>>();
+    write_weekly(out, &events, start_day, end_day)
+}
+
+/// The free windows as events called `Free`.
+pub fn write_free_cal<W: Write>(
+    out: &mut W,
+    windows: &[FreeWindow],
+    start_day: NaiveDate,
+    end_day: NaiveDate,
+) -> io::Result<()> {
+    let events = windows
+        .iter()
+        .map(|w| (w.weekday, "Free", w.start, w.end))
+        .collect::<Vec<_>>();
+    write_weekly(out, &events, start_day, end_day)
+}
+
+/// Repeats each event every week on its day, from `start_day` to `end_day`.
+///
+/// The output only depends on the arguments, so exporting the same timetable
+/// twice gives the same file. That's why the `DTSTAMP` of each event is its
+/// start rather than the time of the export.
+fn write_weekly<W: Write>(
+    out: &mut W,
+    events: &[(WeekDay, &str, NaiveTime, NaiveTime)],
+    start_day: NaiveDate,
+    end_day: NaiveDate,
 ) -> io::Result<()> {
     write!(
         out,
         "{}",
-        time.iter()
-            .map(|table| {
-                table.fold(
-                    HashMap::default(),
-                    |mut acc: HashMap<_, EventBuilder>, (i, tp, c)| {
-                        let t = index_to_time(i);
-                        acc.entry((tp, c))
-                            .and_modify(|e| e.ends(t + Duration::minutes(30)))
-                            .or_insert_with(|| EventBuilder::new(c).starts(t));
-                        acc
-                    },
-                )
+        ALL_DAYS
+            .iter()
+            .map(|day| {
+                events
+                    .iter()
+                    .filter(|(weekday, ..)| weekday == day)
+                    .map(|(_, summary, start, end)| {
+                        let mut e = EventBuilder::new(summary).starts(*start);
+                        e.ends(*end);
+                        e
+                    })
+                    .collect::<Vec<_>>()
             })
             .zip(successors(Some(Weekday::Mon), |w| Some(w.succ())))
             .fold(Calendar::new(), |cal, (day, weekday)| {
                 successors(
-                    Some(match weekday
-                        .number_from_monday()
-                        .checked_sub(start_day.weekday().number_from_monday())
-                    {
-                        Some(x) => start_day + Duration::days(x.into()),
-                        _ => {
-                            start_day + Duration::weeks(1)
-                                - Duration::days(
-                                    start_day.weekday().num_days_from_monday() as i64
-                                        - weekday.num_days_from_monday() as i64,
-                                )
-                        }
-                    }),
+                    Some(
+                        match weekday
+                            .number_from_monday()
+                            .checked_sub(start_day.weekday().number_from_monday())
+                        {
+                            Some(x) => start_day + Duration::days(x.into()),
+                            _ => {
+                                start_day + Duration::weeks(1)
+                                    - Duration::days(
+                                        start_day.weekday().num_days_from_monday() as i64
+                                            - weekday.num_days_from_monday() as i64,
+                                    )
+                            }
+                        },
+                    ),
                     |d| Some(*d + Duration::weeks(1)).filter(|nd| nd <= &end_day),
                 )
                 .flat_map(|date| {
-                    day.values().map(move |event| {
+                    day.iter().map(move |event| {
+                        let start = NaiveDateTime::new(date, event.start.unwrap());
+                        // Set explicitly, otherwise they are generated from the
+                        // clock and a random number, which aren't reproducible
+                        // nor available on wasm.
+                        let stamp = start.format("%Y%m%dT%H%M%SZ").to_string();
                         Event::new()
                             .summary(event.summary)
-                            .starts(NaiveDateTime::new(date, event.start.unwrap()))
+                            .starts(start)
                             .ends(NaiveDateTime::new(date, event.end.unwrap()))
+                            .add_property("DTSTAMP", &stamp)
+                            .uid(&format!("{}-{}@scheduler", stamp, event.summary))
                             .done()
                     })
                 })
@@ -94,3 +139,28 @@ pub fn write_cal<W: Write>(
             })
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_output_every_time() {
+        let schedule = "A:T1:10h00:11h00:Mon".parse().unwrap();
+        let timetable = &TimeTable::all_the_combos(&schedule)[0];
+        let export = || {
+            let mut out = Vec::new();
+            let (start, end) = (
+                NaiveDate::from_ymd(2020, 2, 12),
+                NaiveDate::from_ymd(2020, 2, 24),
+            );
+            write_cal(&mut out, timetable, start, end).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let ical = export();
+        assert_eq!(ical, export());
+        assert_eq!(ical.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ical.contains("DTSTAMP:20200217T100000Z"));
+        assert!(ical.contains("UID:20200224T100000Z-A@scheduler"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Generate every possible timetable from a list of shifts, then narrow them down.
+//!
+//! A schedule is a list of lines in the format `Course:Shift:Start:End:DayOfWeek`,
+//! which is parsed into [`Shifts`]. From it every timetable where no two classes
+//! overlap can be generated, then filtered with [`TimetableFilters`], ordered with
+//! a [`Ranking`] and exported with the functions in [`export`] and `ical`.
+//!
+//! ```
+//! use scheduler::{Ranking, Shifts, TimeTable, TimetableFilters, WeekDay};
+//!
+//! let schedule: Shifts = "PL:T1:10h00:12h00:Mon\n\
+//!                         PL:L1:08h00:10h00:Mon\n\
+//!                         PL:L2:14h00:16h00:Fri"
+//!     .parse()
+//!     .unwrap();
+//! let mut timetables = TimeTable::all_the_combos(&schedule);
+//! Ranking::default().sort(&mut timetables);
+//!
+//! let mut filters = TimetableFilters::default();
+//! filters.free_days.insert(WeekDay::Fri);
+//! assert_eq!(filters.apply(&timetables).len(), 1);
+//! ```
+
+pub mod check;
+pub mod compare;
+pub mod diff;
+pub mod enrolment;
+pub mod error;
+pub mod export;
+pub mod filters;
+pub mod free;
+pub mod group;
+pub mod guide;
+#[cfg(feature = "ical")]
+pub mod ical;
+pub mod lint;
+pub mod matrix;
+pub mod preference;
+pub mod rank;
+pub mod render;
+pub mod replan;
+pub mod session;
+pub mod shifts;
+pub mod stats;
+pub mod swap;
+pub mod theme;
+pub mod timetable;
+pub mod util;
+pub mod vacancy;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use compare::Comparison;
+pub use error::ErrMessage;
+pub use filters::TimetableFilters;
+pub use rank::Ranking;
+pub use render::RenderOptions;
+pub use session::{Bookmark, SavedSession};
+pub use shifts::{Shift, Shifts};
+pub use stats::Statistics;
+pub use theme::{Palette, Theme};
+pub use timetable::{Metrics, Session, TimeTable, TimetableKey};
+pub use util::{Class, ClassType, WeekDay, ALL_DAYS};
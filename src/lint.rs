@@ -0,0 +1,347 @@
+//! Checks for mistakes a schedule file parses fine with, each warned about
+//! with the line it's in.
+//!
+//! Like compiler lints, every [`Rule`] can be turned off, made a warning or
+//! made an error, starting from its [`Rule::default_level`].
+
+use crate::{
+    shifts::Shifts,
+    util::{Class, ClassType, WeekDay},
+};
+use chrono::NaiveTime;
+use enum_iterator::IntoEnumIterator;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Classes are expected between these hours.
+const EARLIEST: u32 = 7;
+const LATEST: u32 = 23;
+
+/// How many times longer a shift can take than another of the same course
+/// and kind before it's suspicious.
+const UNEVEN_FACTOR: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, IntoEnumIterator)]
+pub enum Rule {
+    /// A class that ends before it starts, or when it starts.
+    EndsBeforeStart,
+    /// Two classes of the same shift that overlap, which makes it impossible.
+    OverlapsItself,
+    /// The same class written twice, which is only kept once.
+    DuplicateLine,
+    /// A course with lab shifts but no theory shift.
+    LabWithoutTheory,
+    /// Shifts of a course and kind where one takes much longer than another.
+    UnevenShifts,
+    /// A class before 07h00 or after 23h00.
+    OddHours,
+    /// An eighth column that isn't a curricular year, likely from another tool.
+    InvalidYear,
+    /// Course names that only differ in case or spaces, likely typos.
+    SimilarNames,
+    /// A class on Saturday or Sunday.
+    Weekend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Rule {
+    pub fn default_level(self) -> Level {
+        match self {
+            Self::EndsBeforeStart | Self::OverlapsItself => Level::Deny,
+            Self::Weekend => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+
+    /// The name used to change its level, like `duplicate-line`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::EndsBeforeStart => "ends-before-start",
+            Self::OverlapsItself => "overlaps-itself",
+            Self::DuplicateLine => "duplicate-line",
+            Self::LabWithoutTheory => "lab-without-theory",
+            Self::UnevenShifts => "uneven-shifts",
+            Self::OddHours => "odd-hours",
+            Self::InvalidYear => "invalid-year",
+            Self::SimilarNames => "similar-names",
+            Self::Weekend => "weekend",
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::into_enum_iter()
+            .find(|r| r.name() == s)
+            .ok_or_else(|| format!("No lint called {}", s))
+    }
+}
+
+/// The level of every rule, their default unless changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LintLevels(BTreeMap<Rule, Level>);
+
+impl LintLevels {
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        self.0.insert(rule, level);
+    }
+
+    pub fn get(&self, rule: Rule) -> Level {
+        self.0
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+}
+
+/// Something suspicious in a line of a schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub rule: Rule,
+    pub level: Level,
+    pub line_no: usize,
+    pub message: String,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self.level {
+            Level::Deny => "error",
+            _ => "warning",
+        };
+        write!(
+            f,
+            "line {}: {}[{}]: {}",
+            self.line_no,
+            level,
+            self.rule.name(),
+            self.message
+        )
+    }
+}
+
+fn hours(t: NaiveTime) -> String {
+    t.format("%Hh%M").to_string()
+}
+
+fn same_time(a: &Class, b: &Class) -> bool {
+    (a.weekday, a.start, a.end) == (b.weekday, b.start, b.end)
+}
+
+/// Every lint in `schedule` that isn't allowed in `levels`, by line.
+pub fn lint(schedule: &Shifts, levels: &LintLevels) -> Vec<Lint> {
+    let mut found = Vec::new();
+    let mut push = |rule: Rule, line_no: usize, message: String| {
+        let level = levels.get(rule);
+        if level != Level::Allow {
+            found.push(Lint {
+                rule,
+                level,
+                line_no,
+                message,
+            });
+        }
+    };
+    let classes = schedule.classes();
+    for (i, c) in classes.iter().enumerate() {
+        if c.end <= c.start {
+            push(
+                Rule::EndsBeforeStart,
+                c.line_no,
+                format!("ends at {}, starting at {}", hours(c.end), hours(c.start)),
+            );
+        }
+        let early = NaiveTime::from_hms(EARLIEST, 0, 0);
+        let late = NaiveTime::from_hms(LATEST, 0, 0);
+        if c.start < early || c.end > late {
+            push(
+                Rule::OddHours,
+                c.line_no,
+                format!(
+                    "{}-{} is a strange time for a class",
+                    hours(c.start),
+                    hours(c.end)
+                ),
+            );
+        }
+        if let Some(year) = c.invalid_year() {
+            push(
+                Rule::InvalidYear,
+                c.line_no,
+                format!("{} isn't a curricular year, so it's left out", year),
+            );
+        }
+        if matches!(c.weekday, WeekDay::Sat | WeekDay::Sun) {
+            push(Rule::Weekend, c.line_no, format!("is on {}", c.weekday));
+        }
+        let same_shift = classes[..i]
+            .iter()
+            .filter(|o| o.name == c.name && o.kind == c.kind);
+        for o in same_shift {
+            if same_time(o, c) {
+                push(
+                    Rule::DuplicateLine,
+                    c.line_no,
+                    format!("same class as line {}", o.line_no),
+                );
+            } else if o.weekday == c.weekday && o.start < c.end && c.start < o.end {
+                push(
+                    Rule::OverlapsItself,
+                    c.line_no,
+                    format!(
+                        "overlaps line {} of the same shift, {} {}",
+                        o.line_no,
+                        c.name,
+                        c.kind.code()
+                    ),
+                );
+            }
+        }
+    }
+
+    let mut courses = BTreeMap::<&str, Vec<&Class>>::new();
+    for c in classes {
+        courses.entry(c.name.as_str()).or_default().push(c);
+    }
+    let theory = |c: &Class| matches!(c.kind, ClassType::T(_));
+    for (course, classes) in &courses {
+        let lab = classes.iter().find(|c| !theory(c));
+        if let (Some(lab), false) = (lab, classes.iter().any(|c| theory(c))) {
+            push(
+                Rule::LabWithoutTheory,
+                lab.line_no,
+                format!("{} has labs but no theory shift", course),
+            );
+        }
+        for kind in &[true, false] {
+            // Minutes a week of each shift, and its first line.
+            let mut minutes = BTreeMap::<ClassType, (i64, usize)>::new();
+            for c in classes.iter().filter(|c| theory(c) == *kind) {
+                let entry = minutes.entry(c.kind).or_insert((0, c.line_no));
+                entry.0 += (c.end - c.start).num_minutes().max(0);
+            }
+            let shortest = minutes.iter().min_by_key(|(_, (m, _))| *m);
+            let longest = minutes.iter().max_by_key(|(_, (m, _))| *m);
+            if let (Some((short, (min, _))), Some((long, (max, line_no)))) = (shortest, longest) {
+                if *max > min * UNEVEN_FACTOR {
+                    push(
+                        Rule::UnevenShifts,
+                        *line_no,
+                        format!(
+                            "{} {} takes {} minutes a week, {} only {}",
+                            course,
+                            long.code(),
+                            max,
+                            short.code(),
+                            min
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut names = BTreeMap::<String, Vec<&str>>::new();
+    for course in courses.keys() {
+        let folded = course
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+        names.entry(folded).or_default().push(course);
+    }
+    for similar in names.values().filter(|n| n.len() > 1) {
+        for name in &similar[1..] {
+            push(
+                Rule::SimilarNames,
+                courses[name][0].line_no,
+                format!("{} looks like a typo of {}", name, similar[0]),
+            );
+        }
+    }
+    found.sort_by_key(|l| l.line_no);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(schedule: &str) -> Vec<Rule> {
+        let schedule = schedule.parse().unwrap();
+        lint(&schedule, &LintLevels::default())
+            .into_iter()
+            .map(|l| l.rule)
+            .collect()
+    }
+
+    #[test]
+    fn duplicates_and_overlaps() {
+        assert_eq!(
+            rules("A:T1:10h00:11h00:Mon\nA:T1:10h00:11h00:Mon"),
+            vec![Rule::DuplicateLine]
+        );
+        assert_eq!(
+            rules("A:T1:10h00:11h00:Mon\nA:T1:10h30:11h30:Mon"),
+            vec![Rule::OverlapsItself]
+        );
+        assert_eq!(
+            Rule::OverlapsItself.default_level(),
+            Level::Deny,
+            "an overlapping shift can't be in any timetable"
+        );
+    }
+
+    #[test]
+    fn each_rule() {
+        assert_eq!(rules("A:T1:11h00:10h00:Mon"), vec![Rule::EndsBeforeStart]);
+        assert_eq!(rules("A:L1:10h00:11h00:Mon"), vec![Rule::LabWithoutTheory]);
+        assert_eq!(
+            rules("A:T1:10h00:11h00:Mon\nA:T2:10h00:13h00:Tue"),
+            vec![Rule::UnevenShifts]
+        );
+        assert_eq!(rules("A:T1:06h00:08h00:Mon"), vec![Rule::OddHours]);
+        assert_eq!(
+            rules("Prog:T1:10h00:11h00:Mon\nprog:T1:10h00:11h00:Tue"),
+            vec![Rule::SimilarNames]
+        );
+        assert_eq!(rules("A:T1:10h00:11h00:Sat"), vec![]);
+    }
+
+    #[test]
+    fn levels() {
+        let schedule = "A:T1:10h00:11h00:Sat\nA:T2:06h00:07h00:Mon"
+            .parse()
+            .unwrap();
+        let mut levels = LintLevels::default();
+        assert_eq!(levels.get(Rule::Weekend), Level::Allow);
+        levels.set(Rule::Weekend, Level::Deny);
+        levels.set(Rule::OddHours, Level::Allow);
+        let lints = lint(&schedule, &levels);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].to_string(), "line 1: error[weekend]: is on Sat");
+        assert_eq!("odd-hours".parse(), Ok(Rule::OddHours));
+        assert_eq!("odd".parse::<Rule>(), Err("No lint called odd".to_string()));
+        assert!(Rule::into_enum_iter().all(|r| r.name().parse() == Ok(r)));
+    }
+
+    #[test]
+    fn invalid_year_is_a_warning() {
+        let schedule = "H:T1:18h00:19h00:Mon:Smith:R1:x".parse().unwrap();
+        let lints = lint(&schedule, &LintLevels::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, Rule::InvalidYear);
+        assert_eq!(lints[0].level, Level::Warn);
+        assert_eq!(rules("H:T1:18h00:19h00:Mon:Smith:R1:1"), vec![]);
+    }
+}
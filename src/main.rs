@@ -1,273 +1,268 @@
-mod error;
-mod ical;
-mod shifts;
-mod timetable;
-mod util;
+mod commands;
+mod menu;
+#[cfg(feature = "serve")]
+mod serve;
 
-use chrono::{format::ParseResult, NaiveDate, NaiveTime};
-use dialoguer::{Checkboxes, Input};
-use enum_iterator::IntoEnumIterator;
-use itertools::Itertools;
-use serde::{Deserialize, Serialize};
-use serde_json::{from_reader, to_writer};
-use shifts::Shifts;
+use scheduler::{
+    session::content_hash, Palette, RenderOptions, SavedSession, Shifts, Theme, TimeTable,
+};
 use std::{
-    collections::HashSet,
-    env::args,
-    error::Error,
-    fmt::{self, Display},
-    fs::File,
-    io::{self, BufRead, BufReader},
+    env::{self, args},
+    fmt::Display,
+    fs::{self, File},
+    io,
+    path::PathBuf,
+    str::FromStr,
 };
-use timetable::TimeTable;
-use util::{ClassType, WeekDay, ALL_DAYS};
 
 fn main() -> io::Result<()> {
-    let schedule = match Shifts::parse_schedule(
-        BufReader::new(File::open(
-            args().nth(1).expect("No schedule file provided"),
-        )?)
-        .lines()
-        .filter_map(Result::ok)
-        .filter(|x| !x.is_empty()),
-    ) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("{}", e);
-            return Err(io::Error::from(io::ErrorKind::Other));
-        }
-    };
-    let mut tts: Vec<TimeTable> = (&schedule).into();
-    tts.sort_by_cached_key(|t| -t.sum_work_hours());
-    let mut filters = TimetableFilters::default();
-    let mut feedback = String::new();
-    loop {
-        tts.retain(|t| filters.filter(t));
-        let amount = tts.len();
-        for t in &tts {
-            println!("{}", t);
-        }
-        println!("Number of possible timetables: {}", amount);
-        if !feedback.is_empty() {
-            println!("{}", feedback);
+    let mut args = args().skip(1).collect::<Vec<_>>();
+    if let Some(jobs) = parse_option(&mut args, "--jobs", "--jobs needs a number of threads")? {
+        set_jobs(jobs);
+    }
+    let theme = load_theme(&mut args)?;
+    let render = render_options(&mut args);
+    let session_path = option(&mut args, "--session")
+        .map(PathBuf::from)
+        .or_else(|| config_dir().map(|c| c.join("session.json")));
+    let resume = flag(&mut args, "--resume");
+    match args.first().map(String::as_str) {
+        #[cfg(feature = "serve")]
+        Some("serve") => {
+            let port =
+                parse_option(&mut args, "--port", "--port needs a port number")?.unwrap_or(8080);
+            let schedule = args.get(1).map(|p| load_schedule(p)).transpose()?;
+            serve::serve(schedule, port).map_err(io::Error::other)
         }
-        if let Some(f) = filters.prompt(&mut feedback, tts.iter()) {
-            filters = f;
-        } else {
-            break Ok(());
+        Some("group") => commands::group(&mut args, &theme, &render),
+        Some("free") => commands::free(&mut args, &theme, &render),
+        Some("swap") => commands::swap(&mut args, &theme, &render),
+        Some("replan") => commands::replan(&mut args, &theme, &render),
+        Some("diff") => commands::diff(&mut args, &theme),
+        Some("check") => commands::check(&args),
+        Some("matrix") => commands::matrix(&mut args),
+        Some("lint") => commands::lint(&mut args),
+        Some("vacancies") => commands::vacancies(&mut args, &theme, &render),
+        path => {
+            let mut session = match (resume, &session_path) {
+                (true, Some(p)) if p.exists() => SavedSession::load(p)?,
+                (true, Some(p)) => {
+                    eprintln!("No session in {}, starting a new one", p.display());
+                    SavedSession::default()
+                }
+                (true, None) => return Err(usage("No session to resume, pass one with --session")),
+                (false, _) => SavedSession::default(),
+            };
+            if let Some(path) = path {
+                session.schedule = Some(fs::canonicalize(path)?);
+            }
+            interactive(session, session_path, theme, render)
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, IntoEnumIterator)]
-pub enum SubMenus {
-    StartsAfter,
-    EndsBefore,
-    HasFreeDay,
-    HasAShift,
-    HasntAShift,
-    SaveFilters,
-    LoadFilters,
-    ExportToIcal,
-    Close,
+/// Removes `name` from `args`, returning whether it was there.
+pub(crate) fn flag(args: &mut Vec<String>, name: &str) -> bool {
+    let len = args.len();
+    args.retain(|a| a != name);
+    args.len() != len
+}
+
+/// Removes `name` and the argument after it from `args`, returning the latter.
+pub(crate) fn option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == name)?;
+    args.remove(i);
+    (i < args.len()).then(|| args.remove(i))
+}
+
+/// Like [`option`], parsing the argument, and printing `message` when it can't
+/// be parsed.
+pub(crate) fn parse_option<T: FromStr>(
+    args: &mut Vec<String>,
+    name: &str,
+    message: &str,
+) -> io::Result<Option<T>> {
+    option(args, name)
+        .map(|a| a.parse().map_err(|_| usage(message)))
+        .transpose()
 }
 
-impl Display for SubMenus {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use SubMenus::*;
-        let s = match self {
-            StartsAfter => "Starts after",
-            EndsBefore => "Ends before",
-            HasFreeDay => "Has free day",
-            HasAShift => "Has a shift",
-            HasntAShift => "Hasn't a shift",
-            SaveFilters => "Save filters",
-            LoadFilters => "Load filters",
-            ExportToIcal => "Export as iCal",
-            Close => "Close",
+/// Prints `message`, returning the error to exit with when the arguments can't
+/// be used.
+pub(crate) fn usage<M: Display>(message: M) -> io::Error {
+    eprintln!("{}", message);
+    io::Error::from(io::ErrorKind::Other)
+}
+
+#[cfg(feature = "parallel")]
+fn set_jobs(jobs: usize) {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .expect("Failed to start the thread pool")
+}
+
+#[cfg(not(feature = "parallel"))]
+fn set_jobs(_: usize) {
+    eprintln!("Built without the parallel feature, --jobs is ignored");
+}
+
+/// `scheduler` in `$XDG_CONFIG_HOME`, or in `~/.config`.
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|c| c.join("scheduler"))
+}
+
+/// Reads the theme from `--theme`, or from `scheduler/theme.json` in the
+/// config directory if it exists, then applies `--palette`, `--no-color` and
+/// `NO_COLOR` on top of it.
+fn load_theme(args: &mut Vec<String>) -> io::Result<Theme> {
+    let default_path = || {
+        config_dir()
+            .map(|c| c.join("theme.json"))
+            .filter(|p| p.exists())
+    };
+    let mut theme = match option(args, "--theme")
+        .map(PathBuf::from)
+        .or_else(default_path)
+    {
+        Some(path) => serde_json::from_reader(File::open(path)?)?,
+        None => Theme::default(),
+    };
+    if let Some(palette) = option(args, "--palette") {
+        theme.palette = match palette.as_str() {
+            "basic" => Palette::Basic,
+            "256" => Palette::Ansi256,
+            "truecolor" => Palette::TrueColor,
+            "colorblind" => Palette::ColorBlind,
+            "none" => Palette::None,
+            _ => {
+                return Err(usage(
+                    "--palette has to be basic, 256, truecolor, colorblind or none",
+                ))
+            }
         };
-        write!(f, "{}", s)
     }
+    if flag(args, "--no-color") || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        theme = Theme::no_color();
+    }
+    Ok(theme)
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct TimetableFilters {
-    starts_after: Option<NaiveTime>,
-    ends_before: Option<NaiveTime>,
-    free_days: HashSet<WeekDay>,
-    has_the_shift: Vec<(ClassType, String)>,
-    hasnt_the_shift: Vec<(ClassType, String)>,
+/// How to draw the grids, from `--borders`, `--compact`, `--all-days` and
+/// `--no-legend`. Names are shortened to fit the terminal, if there is one.
+fn render_options(args: &mut Vec<String>) -> RenderOptions {
+    RenderOptions {
+        borders: flag(args, "--borders"),
+        compact: flag(args, "--compact"),
+        all_days: flag(args, "--all-days"),
+        legend: !flag(args, "--no-legend"),
+        width: console::Term::stdout()
+            .size_checked()
+            .map(|(_, cols)| cols as usize),
+    }
 }
 
-impl TimetableFilters {
-    pub fn filter(&self, timetable: &TimeTable) -> bool {
-        self.starts_after
-            .map_or(true, |t| timetable.starts_after(t))
-            && self.ends_before.map_or(true, |t| timetable.ends_before(t))
-            && self.free_days.iter().all(|d| timetable.free_day(*d))
-            && self
-                .has_the_shift
-                .iter()
-                .all(|(s, t)| timetable.has_the_shift(*s, &t))
-            && self
-                .hasnt_the_shift
-                .iter()
-                .all(|(s, t)| timetable.hasnt_the_shift(*s, &t))
+pub(crate) fn load_schedule(path: &str) -> io::Result<Shifts> {
+    parse_schedule(&fs::read(path)?)
+}
+
+fn parse_schedule(contents: &[u8]) -> io::Result<Shifts> {
+    match String::from_utf8_lossy(contents).parse() {
+        Ok(s) => Ok(s),
+        Err(e) => Err(usage(e)),
     }
+}
 
-    pub fn prompt<'a>(
-        mut self,
-        feedback: &mut String,
-        timetables: impl Iterator<Item = &'a TimeTable>,
-    ) -> Option<Self> {
-        feedback.clear();
-        let submenus = SubMenus::into_enum_iter().collect::<Vec<_>>();
-        let pick = Input::new()
-            .with_prompt(&format!(
-                "Filters:\n{}\nPick one",
-                submenus
-                    .iter()
-                    .enumerate()
-                    .format_with("\n", |(i, p), f| f(&format_args!("{}) {}", i, p)))
-            ))
-            .interact();
-        match pick.map(|i: usize| submenus[i]) {
-            Ok(SubMenus::StartsAfter) => {
-                let input = Input::<String>::new()
-                    .with_prompt("Time")
-                    .allow_empty(true)
-                    .interact()
-                    .unwrap();
-                if let Ok(t) = parse_time(&input) {
-                    self.starts_after = Some(t)
-                } else {
-                    self.starts_after = None;
-                    feedback.push_str("Cleared")
-                }
-            }
-            Ok(SubMenus::EndsBefore) => {
-                let input = Input::<String>::new()
-                    .with_prompt("Time")
-                    .allow_empty(true)
-                    .interact()
-                    .unwrap();
-                if let Ok(t) = parse_time(&input) {
-                    self.ends_before = Some(t)
-                } else {
-                    self.ends_before = None;
-                    feedback.push_str("Cleared")
-                }
-            }
-            Ok(SubMenus::HasFreeDay) => {
-                let checked = ALL_DAYS
-                    .iter()
-                    .map(|d| (d, self.free_days.contains(d)))
-                    .collect::<Vec<_>>();
-                let selection = Checkboxes::new()
-                    .items_checked(&checked)
-                    .interact()
-                    .unwrap();
-                self.free_days.clear();
-                for d in selection {
-                    self.free_days.insert(*checked[d].0);
-                }
-            }
-            Ok(SubMenus::HasAShift) => {
-                let k = Input::new()
-                    .with_prompt("Shift [T{{number}}/L{{number}}]")
-                    .interact();
-                let name = Input::new().with_prompt("Course").interact().unwrap();
-                if let Ok(k) = k {
-                    self.has_the_shift.push((k, name));
-                } else {
-                    self.has_the_shift.clear();
-                    feedback.push_str("Cleared, press enter");
-                }
-            }
-            Ok(SubMenus::HasntAShift) => {
-                let k = Input::new()
-                    .with_prompt("Shift [T{{number}}/L{{number}}]")
-                    .interact();
-                let name = Input::new().with_prompt("Course").interact().unwrap();
-                if let Ok(k) = k {
-                    self.hasnt_the_shift.push((k, name));
-                } else {
-                    self.hasnt_the_shift.clear();
-                    feedback.push_str("Cleared, press enter");
-                }
-            }
-            Ok(SubMenus::SaveFilters) => {
-                let k = Input::<String>::new()
-                    .with_prompt("Filename")
-                    .interact()
-                    .map_err(|e| Box::new(e) as Box<dyn Error>)
-                    .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))
-                    .and_then(|f| to_writer(f, &self).map_err(|e| Box::new(e) as Box<dyn Error>));
-                match k {
-                    Ok(_) => feedback.push_str("Saved!"),
-                    Err(e) => feedback.push_str(&format!("Error saving filters: {}", e)),
-                }
-            }
-            Ok(SubMenus::LoadFilters) => {
-                let k = Input::<String>::new()
-                    .with_prompt("Filename")
-                    .interact()
-                    .map_err(|e| Box::new(e) as Box<dyn Error>)
-                    .and_then(|f| File::open(f).map_err(|e| Box::new(e) as Box<dyn Error>))
-                    .and_then(|f| from_reader(f).map_err(|e| Box::new(e) as Box<dyn Error>));
-                match k {
-                    Ok(f) => {
-                        feedback.push_str("Loaded!");
-                        self = f
-                    }
-                    Err(e) => feedback.push_str(&format!("Error saving filters: {}", e)),
-                }
+/// Shows the timetables and asks what to do next until closed, saving the
+/// session to `session_path` after every change.
+fn interactive(
+    mut session: SavedSession,
+    session_path: Option<PathBuf>,
+    theme: Theme,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let path = session
+        .schedule
+        .clone()
+        .ok_or_else(|| usage("No schedule file provided"))?;
+    let contents = fs::read(&path)?;
+    if session.schedule_changed(&contents) {
+        eprintln!(
+            "{} changed since the session was saved, the filters and bookmarks might not match anymore",
+            path.display()
+        );
+    }
+    session.schedule_hash = Some(content_hash(&contents));
+    let schedule = parse_schedule(&contents)?;
+    let mut tts = TimeTable::all_the_combos(&schedule);
+    let styles = theme.styles(schedule.courses());
+    let mut feedback = String::new();
+    let mut saved = None;
+    loop {
+        if let Some(p) = session_path
+            .as_ref()
+            .filter(|_| saved.as_ref() != Some(&session))
+        {
+            match session.save(p) {
+                Ok(()) => saved = Some(session.clone()),
+                Err(e) => eprintln!("Error saving the session: {}", e),
             }
-            Ok(SubMenus::Close) => return None,
-            Ok(SubMenus::ExportToIcal) => {
-                let k = || -> Result<(), Box<dyn Error>> {
-                    let mut file = Input::<String>::new()
-                        .with_prompt("Filename")
-                        .interact()
-                        .map_err(|e| Box::new(e) as Box<dyn Error>)
-                        .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))?;
-                    let time_table = match timetables.exactly_one() {
-                        Ok(t) => t,
-                        Err(_) => return Err("Either too many timetables or too few".into()),
-                    };
-                    let start_date = Input::<NaiveDate>::new()
-                        .with_prompt("Start date: YY-MM-DD")
-                        .interact()
-                        .map_err(|e| Box::new(e) as Box<dyn Error>)?;
-                    let end_date = Input::<NaiveDate>::new()
-                        .with_prompt("End date: YY-MM-DD")
-                        .interact()
-                        .map_err(|e| Box::new(e) as Box<dyn Error>)?;
-                    ical::write_cal(&mut file, time_table, start_date, end_date)
-                        .map_err(|e| Box::new(e) as Box<dyn Error>)
-                }();
-                match k {
-                    Ok(_) => feedback.push_str("Saved!"),
-                    Err(e) => feedback.push_str(&format!("Error exporting to iCal: {}", e)),
-                }
+        }
+        session.ranking.sort(&mut tts);
+        let filtered = session.filters.apply(&tts);
+        // Best last, so it's the closest to the prompt.
+        for (i, t) in filtered.iter().enumerate().rev() {
+            println!("#{}\n{}", i + 1, t.render(&styles, &options));
+        }
+        let position = |key| filtered.iter().position(|t| t.matches(key));
+        if let Some(key) = &session.selected {
+            match position(key) {
+                Some(i) => println!(
+                    "Selected #{}\n{}",
+                    i + 1,
+                    filtered[i].render(&styles, &options)
+                ),
+                None => println!("The selected timetable was filtered out"),
             }
-            Err(_) => feedback.push_str("Invalid choice"),
         }
-        Some(self)
+        if !session.bookmarks.is_empty() {
+            let shown = session
+                .bookmarks
+                .iter()
+                .map(|b| {
+                    position(&b.shifts).map_or("filtered out".into(), |i| format!("#{}", i + 1))
+                })
+                .collect::<Vec<_>>();
+            println!("Bookmarked: {}", shown.join(", "));
+        }
+        println!("Number of possible timetables: {}", filtered.len());
+        if !feedback.is_empty() {
+            println!("{}", feedback);
+        }
+        if !menu::prompt(
+            &mut session,
+            &mut feedback,
+            &schedule,
+            &theme,
+            &options,
+            filtered.into_iter(),
+        ) {
+            break Ok(());
+        }
     }
 }
 
-fn parse_time(s: &str) -> ParseResult<NaiveTime> {
-    match s {
-        _ if s.len() < 3 => Ok(NaiveTime::from_hms(
-            s.parse()
-                .map_err(|_| NaiveTime::parse_from_str(s, "%H:%M").unwrap_err())?,
-            0,
-            0,
-        )),
-        _ if s.contains("h") => NaiveTime::parse_from_str(s, "%Hh%M"),
-        _ if s.contains(":") => NaiveTime::parse_from_str(s, "%H:%M")
-            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S")),
-        _ => NaiveTime::parse_from_str(s, "%H:%M"),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_utf8_doesnt_stop_parsing() {
+        let contents = b"A:T1:10h00:11h00:Mon:Jo\xe3o\nB:T1:11h00:12h00:Mon\n";
+        let schedule = parse_schedule(contents).unwrap();
+        assert_eq!(schedule.courses().collect::<Vec<_>>(), vec!["A", "B"]);
     }
 }
@@ -0,0 +1,274 @@
+//! How much each pair of courses gets in each other's way: of all the pairs of
+//! shifts, one of each course, how many clash, and which.
+//!
+//! Theory and lab shifts are counted as separate pairs rather than as one
+//! choice per course, so with A having 2 theory and 3 lab shifts and B having
+//! 2 theory shifts, A and B have 10 pairs, not the 12 ways of picking shifts.
+//!
+//! Two shifts clash when they take up the same half hour, which is the check
+//! [`TimeTable::add`](crate::TimeTable::add) makes. The same pairs are used
+//! before generating timetables to leave out the shifts that clash with every
+//! shift of some other course, since no timetable can have them.
+
+use crate::{
+    shifts::{ClassSet, Shift, Shifts},
+    util::ClassType,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    io::{self, Write},
+};
+
+/// Whether `a` and `b` can't be in the same timetable.
+pub fn clash(a: &Shift, b: &Shift) -> bool {
+    a.occupancy()
+        .iter()
+        .zip(b.occupancy())
+        .any(|(a, b)| a & b != 0)
+}
+
+/// How a pair of courses clash.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PairConflicts {
+    /// How many pairs of shifts there are, one from each course, whatever
+    /// their kind. For a course with itself, one theory and one lab shift.
+    pub combinations: usize,
+    /// The pairs of shifts that clash.
+    pub clashing: Vec<(ClassType, ClassType)>,
+}
+
+impl PairConflicts {
+    /// From 0, never clash, to 1, always do.
+    pub fn fraction(&self) -> f64 {
+        match self.combinations {
+            0 => 0.0,
+            n => self.clashing.len() as f64 / n as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConflictMatrix<'a> {
+    pub courses: Vec<&'a str>,
+    /// By the indices of the courses in [`ConflictMatrix::courses`], with the
+    /// first never greater than the second.
+    pub pairs: BTreeMap<(usize, usize), PairConflicts>,
+}
+
+#[derive(Serialize)]
+struct PairExport<'a> {
+    a: &'a str,
+    b: &'a str,
+    shift_pairs: usize,
+    fraction: f64,
+    clashing: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct MatrixExport<'a> {
+    courses: &'a [&'a str],
+    pairs: Vec<PairExport<'a>>,
+}
+
+impl<'a> ConflictMatrix<'a> {
+    pub fn new(schedule: &'a Shifts) -> Self {
+        let courses = schedule.courses().collect::<Vec<_>>();
+        let shifts = courses
+            .iter()
+            .map(|c| schedule.shifts().filter(|s| s.course == *c).collect())
+            .collect::<Vec<Vec<_>>>();
+        let mut pairs = BTreeMap::new();
+        for (i, a) in shifts.iter().enumerate() {
+            for (j, b) in shifts.iter().enumerate().skip(i) {
+                let mut pair = PairConflicts::default();
+                for s in a {
+                    for t in b {
+                        let theory_and_lab = s.kind.unique_id() > 0 && t.kind.unique_id() < 0;
+                        if i == j && !theory_and_lab {
+                            continue;
+                        }
+                        pair.combinations += 1;
+                        if clash(s, t) {
+                            pair.clashing.push((s.kind, t.kind));
+                        }
+                    }
+                }
+                pairs.insert((i, j), pair);
+            }
+        }
+        Self { courses, pairs }
+    }
+
+    fn exports(&self) -> Vec<PairExport<'_>> {
+        self.pairs
+            .iter()
+            .map(|((i, j), p)| PairExport {
+                a: self.courses[*i],
+                b: self.courses[*j],
+                shift_pairs: p.combinations,
+                fraction: p.fraction(),
+                clashing: p
+                    .clashing
+                    .iter()
+                    .map(|(s, t)| (s.code(), t.code()))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn write_json<W: Write>(&self, out: W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(
+            out,
+            &MatrixExport {
+                courses: &self.courses,
+                pairs: self.exports(),
+            },
+        )
+    }
+
+    /// One row per pair, with the header
+    /// `course_a,course_b,shift_pairs,fraction,clashing`, where `shift_pairs`
+    /// is [`PairConflicts::combinations`] and `clashing` lists the pairs of
+    /// shifts that clash like `L1-T2 L3-T2`.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "course_a,course_b,shift_pairs,fraction,clashing")?;
+        for p in self.exports() {
+            let clashing = p
+                .clashing
+                .iter()
+                .map(|(s, t)| format!("{}-{}", s, t))
+                .collect::<Vec<_>>();
+            writeln!(
+                out,
+                "{},{},{},{:.3},{}",
+                p.a,
+                p.b,
+                p.shift_pairs,
+                p.fraction,
+                clashing.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The percentage of pairs of shifts that clash for every pair of courses.
+impl Display for ConflictMatrix<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .courses
+            .iter()
+            .map(|c| c.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        write!(f, "{:width$}", "", width = width)?;
+        for c in &self.courses {
+            write!(f, " {:>width$}", c, width = width)?;
+        }
+        writeln!(f)?;
+        for (i, c) in self.courses.iter().enumerate() {
+            write!(f, "{:width$}", c, width = width)?;
+            for j in 0..self.courses.len() {
+                let cell = match self.pairs.get(&(i.min(j), i.max(j))) {
+                    Some(p) if p.combinations > 0 => format!("{:.0}%", p.fraction() * 100.0),
+                    _ => "-".to_string(),
+                };
+                write!(f, " {:>width$}", cell, width = width)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Leaves out of `classes` the shifts that overlap themselves or clash with
+/// every shift of some other course, until there are none left to leave out.
+pub(crate) fn prune(classes: &mut ClassSet) {
+    loop {
+        let dead = classes
+            .iter()
+            .flat_map(|(group, shifts)| shifts.iter().map(move |s| (group, s)))
+            .filter(|(group, s)| {
+                s.overlaps_itself()
+                    || classes
+                        .iter()
+                        .filter(|(other, _)| other != group)
+                        .any(|(_, others)| others.iter().all(|o| clash(s, o)))
+            })
+            .map(|(group, s)| (*group, s.kind))
+            .collect::<Vec<_>>();
+        if dead.is_empty() {
+            break;
+        }
+        for (group, kind) in dead {
+            if let Some(shifts) = classes.get_mut(&group) {
+                shifts.retain(|s| s.kind != kind);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        A:L1:10h30:11h30:Mon\n\
+                        B:T1:10h00:11h00:Mon"
+            .parse()
+            .unwrap();
+        let matrix = ConflictMatrix::new(&schedule);
+        assert_eq!(matrix.courses, vec!["A", "B"]);
+        assert_eq!(
+            matrix.pairs[&(0, 0)],
+            PairConflicts {
+                combinations: 2,
+                clashing: vec![(ClassType::T(1), ClassType::L(1))],
+            }
+        );
+        assert_eq!(matrix.pairs[&(0, 1)].clashing.len(), 2);
+        assert_eq!(matrix.pairs[&(1, 1)].fraction(), 0.0);
+        assert_eq!(
+            matrix.to_string(),
+            "        A    B\n\
+             A     50%  67%\n\
+             B     67%    -\n"
+        );
+        let mut csv = Vec::new();
+        matrix.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "course_a,course_b,shift_pairs,fraction,clashing\n\
+             A,A,2,0.500,T1-L1\n\
+             A,B,3,0.667,L1-T1 T1-T1\n\
+             B,B,0,0.000,\n"
+        );
+    }
+
+    #[test]
+    fn pruning() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        B:T1:10h00:11h00:Mon\n\
+                        C:T1:10h00:11h00:Wed\n\
+                        C:T2:10h00:12h00:Thu\n\
+                        C:T2:11h00:12h00:Thu"
+            .parse::<Shifts>()
+            .unwrap();
+        let mut classes = schedule.class_set();
+        prune(&mut classes);
+        let left = classes
+            .values()
+            .flatten()
+            .map(|s| format!("{} {}", s.course, s.kind))
+            .collect::<Vec<_>>();
+        // A T1 always clashes with B, and C T2 with itself.
+        assert_eq!(left, vec!["A T2", "B T1", "C T1"]);
+    }
+}
@@ -0,0 +1,509 @@
+use chrono::{format::ParseResult, NaiveDate, NaiveTime};
+use dialoguer::{Checkboxes, Input, Select};
+use enum_iterator::IntoEnumIterator;
+use itertools::Itertools;
+use scheduler::{
+    export, guide, ical, preference, Bookmark, Comparison, RenderOptions, SavedSession, Shifts,
+    Statistics, Theme, TimeTable, ALL_DAYS,
+};
+use serde_json::{from_reader, to_writer};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::File,
+    io::Write,
+};
+
+/// How many timetables guided narrowing stops at.
+const ENOUGH: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, IntoEnumIterator)]
+pub enum SubMenus {
+    StartsAfter,
+    EndsBefore,
+    HasFreeDay,
+    HasAShift,
+    HasntAShift,
+    Select,
+    Bookmark,
+    Bookmarks,
+    Compare,
+    Statistics,
+    Guide,
+    Preferences,
+    SaveFilters,
+    LoadFilters,
+    ExportToIcal,
+    ExportToJson,
+    ExportToCsv,
+    ExportToSchedule,
+    Close,
+}
+
+impl Display for SubMenus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SubMenus::*;
+        let s = match self {
+            StartsAfter => "Starts after",
+            EndsBefore => "Ends before",
+            HasFreeDay => "Has free day",
+            HasAShift => "Has a shift",
+            HasntAShift => "Hasn't a shift",
+            Select => "Select timetable",
+            Bookmark => "Bookmark selected timetable",
+            Bookmarks => "Bookmarks",
+            Compare => "Compare timetables",
+            Statistics => "Statistics",
+            Guide => "Guided narrowing",
+            Preferences => "Enrolment preferences",
+            SaveFilters => "Save filters",
+            LoadFilters => "Load filters",
+            ExportToIcal => "Export as iCal",
+            ExportToJson => "Export as JSON",
+            ExportToCsv => "Export as CSV",
+            ExportToSchedule => "Export chosen shifts",
+            Close => "Close",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Asks for and runs one action, returning whether to keep going.
+pub fn prompt<'a>(
+    session: &mut SavedSession,
+    feedback: &mut String,
+    schedule: &Shifts,
+    theme: &Theme,
+    options: &RenderOptions,
+    timetables: impl Iterator<Item = &'a TimeTable>,
+) -> bool {
+    let SavedSession {
+        filters,
+        selected,
+        bookmarks,
+        ..
+    } = session;
+    feedback.clear();
+    let submenus = SubMenus::into_enum_iter().collect::<Vec<_>>();
+    let pick = Input::new()
+        .with_prompt(&format!(
+            "Filters:\n{}\nPick one",
+            submenus
+                .iter()
+                .enumerate()
+                .format_with("\n", |(i, p), f| f(&format_args!("{}) {}", i, p)))
+        ))
+        .interact();
+    match pick.map(|i: usize| submenus[i]) {
+        Ok(SubMenus::StartsAfter) => {
+            let input = Input::<String>::new()
+                .with_prompt("Time")
+                .allow_empty(true)
+                .interact()
+                .unwrap();
+            if let Ok(t) = parse_time(&input) {
+                filters.starts_after = Some(t)
+            } else {
+                filters.starts_after = None;
+                feedback.push_str("Cleared")
+            }
+        }
+        Ok(SubMenus::EndsBefore) => {
+            let input = Input::<String>::new()
+                .with_prompt("Time")
+                .allow_empty(true)
+                .interact()
+                .unwrap();
+            if let Ok(t) = parse_time(&input) {
+                filters.ends_before = Some(t)
+            } else {
+                filters.ends_before = None;
+                feedback.push_str("Cleared")
+            }
+        }
+        Ok(SubMenus::HasFreeDay) => {
+            let checked = ALL_DAYS
+                .iter()
+                .map(|d| (d, filters.free_days.contains(d)))
+                .collect::<Vec<_>>();
+            let selection = Checkboxes::new()
+                .items_checked(&checked)
+                .interact()
+                .unwrap();
+            filters.free_days.clear();
+            for d in selection {
+                filters.free_days.insert(*checked[d].0);
+            }
+        }
+        Ok(SubMenus::HasAShift) => {
+            let k = Input::new()
+                .with_prompt("Shift [T{{number}}/L{{number}}]")
+                .interact();
+            let name = Input::new().with_prompt("Course").interact().unwrap();
+            if let Ok(k) = k {
+                filters.has_the_shift.push((k, name));
+            } else {
+                filters.has_the_shift.clear();
+                feedback.push_str("Cleared, press enter");
+            }
+        }
+        Ok(SubMenus::HasntAShift) => {
+            let k = Input::new()
+                .with_prompt("Shift [T{{number}}/L{{number}}]")
+                .interact();
+            let name = Input::new().with_prompt("Course").interact().unwrap();
+            if let Ok(k) = k {
+                filters.hasnt_the_shift.push((k, name));
+            } else {
+                filters.hasnt_the_shift.clear();
+                feedback.push_str("Cleared, press enter");
+            }
+        }
+        Ok(SubMenus::Select) => {
+            let n = Input::<usize>::new()
+                .with_prompt("Timetable number")
+                .interact()
+                .unwrap_or(0);
+            match n.checked_sub(1).and_then(|i| timetables.into_iter().nth(i)) {
+                Some(t) => *selected = Some(t.key()),
+                None => feedback.push_str(&format!("No timetable {}", n)),
+            }
+        }
+        Ok(SubMenus::Bookmark) => match selected {
+            Some(key) => {
+                let existing = bookmarks.iter().position(|b| b.shifts == *key);
+                let note = Input::<String>::new()
+                    .with_prompt("Note")
+                    .with_initial_text(existing.map_or("", |i| &bookmarks[i].note))
+                    .allow_empty(true)
+                    .interact()
+                    .unwrap_or_default();
+                match existing {
+                    Some(i) => bookmarks[i].note = note,
+                    None => bookmarks.push(Bookmark {
+                        shifts: key.clone(),
+                        note,
+                    }),
+                }
+                feedback.push_str("Bookmarked!");
+            }
+            None => feedback.push_str("Select a timetable first"),
+        },
+        Ok(SubMenus::Bookmarks) => {
+            let k = || -> Result<(), Box<dyn Error>> {
+                if bookmarks.is_empty() {
+                    return Err("There are none yet".into());
+                }
+                let timetables = timetables.collect::<Vec<_>>();
+                let list = bookmarks
+                    .iter()
+                    .enumerate()
+                    .format_with("\n", |(i, b), f| {
+                        let place = timetables
+                            .iter()
+                            .position(|t| t.matches(&b.shifts))
+                            .map_or("filtered out".to_string(), |p| format!("#{}", p + 1));
+                        f(&format_args!("{}) {} {}", i + 1, place, b.note))
+                    })
+                    .to_string();
+                // Rebuilt from the schedule, so that the ones filtered out can
+                // be compared and exported too.
+                let rebuilt = || {
+                    bookmarks
+                        .iter()
+                        .enumerate()
+                        .map(|(i, b)| {
+                            TimeTable::from_key(schedule, &b.shifts)
+                                .map(|t| (format!("Bookmark {}", i + 1), t))
+                                .ok_or_else(|| {
+                                    format!("Bookmark {} isn't in the schedule anymore", i + 1)
+                                })
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                };
+                let pick_one = || -> Result<usize, Box<dyn Error>> {
+                    let n = Input::<usize>::new().with_prompt("Bookmark").interact()?;
+                    n.checked_sub(1)
+                        .filter(|i| *i < bookmarks.len())
+                        .ok_or_else(|| format!("No bookmark {}", n).into())
+                };
+                let actions = ["Compare", "Export as JSON", "Select", "Remove", "Back"];
+                match Select::new()
+                    .with_prompt(&format!("Bookmarks:\n{}", list))
+                    .items(&actions)
+                    .default(0)
+                    .interact()?
+                {
+                    0 => {
+                        let rebuilt = rebuilt()?;
+                        let comparison = Comparison::new(
+                            rebuilt
+                                .iter()
+                                .map(|(label, t)| (label.clone(), t))
+                                .collect(),
+                        );
+                        let styles = theme.styles(schedule.courses());
+                        feedback.push_str(&format!(
+                            "{}\n{}",
+                            comparison.side_by_side(&styles, options),
+                            comparison
+                        ));
+                    }
+                    1 => {
+                        let rebuilt = rebuilt()?;
+                        let file = Input::<String>::new()
+                            .with_prompt("Filename")
+                            .interact()
+                            .map_err(|e| Box::new(e) as Box<dyn Error>)
+                            .and_then(|f| {
+                                File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>)
+                            })?;
+                        export::write_json(file, rebuilt.iter().map(|(_, t)| t))?;
+                        feedback.push_str("Saved!");
+                    }
+                    2 => *selected = Some(bookmarks[pick_one()?].shifts.clone()),
+                    3 => {
+                        bookmarks.remove(pick_one()?);
+                        feedback.push_str("Removed");
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }();
+            if let Err(e) = k {
+                feedback.push_str(&format!("Error with bookmarks: {}", e));
+            }
+        }
+        Ok(SubMenus::Compare) => {
+            let k = || -> Result<String, Box<dyn Error>> {
+                let timetables = timetables.collect::<Vec<_>>();
+                let picked = Input::<String>::new()
+                    .with_prompt("Timetables to compare, like: 1 3")
+                    .interact()?
+                    .split_whitespace()
+                    .map(|n| {
+                        n.trim_start_matches('#')
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| {
+                                Some((format!("#{}", i), *timetables.get(i.checked_sub(1)?)?))
+                            })
+                            .ok_or_else(|| format!("No timetable {}", n))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if picked.len() < 2 {
+                    return Err("Pick at least two timetables".into());
+                }
+                let comparison = Comparison::new(picked);
+                let styles = theme.styles(schedule.courses());
+                let views = ["Side by side", "Overlay"];
+                let grid = match Select::new().items(&views).default(0).interact()? {
+                    0 => comparison.side_by_side(&styles, options),
+                    _ => comparison
+                        .overlay(&styles, theme.highlight())
+                        .render(options),
+                };
+                Ok(format!("{}\n{}", grid, comparison))
+            }();
+            match k {
+                Ok(c) => feedback.push_str(&c),
+                Err(e) => feedback.push_str(&format!("Error comparing: {}", e)),
+            }
+        }
+        Ok(SubMenus::Statistics) => {
+            let stats = Statistics::new(schedule, &timetables.collect::<Vec<_>>());
+            feedback.push_str(&format!(
+                "{}\nHow often each half hour is busy:\n{}",
+                stats,
+                stats.heatmap_grid().render(options)
+            ));
+        }
+        Ok(SubMenus::Guide) => {
+            let mut remaining = timetables.collect::<Vec<_>>();
+            let mut skip = Vec::new();
+            while remaining.len() > ENOUGH {
+                let (question, counts) = match guide::next_question(&remaining, &skip) {
+                    Some(q) => q,
+                    None => break,
+                };
+                let mut items = question
+                    .options()
+                    .into_iter()
+                    .zip(&counts)
+                    .map(|(o, n)| format!("{} ({}%)", o, n * 100 / remaining.len()))
+                    .collect::<Vec<_>>();
+                items.push("Don't care".into());
+                items.push("Stop".into());
+                let pick = Select::new()
+                    .with_prompt(&format!(
+                        "{} ({} timetables left)",
+                        question,
+                        remaining.len()
+                    ))
+                    .items(&items)
+                    .default(0)
+                    .interact()
+                    .unwrap();
+                match pick {
+                    _ if pick < counts.len() => {
+                        question.answer(pick, filters);
+                        remaining.retain(|t| filters.filter(t));
+                    }
+                    _ if pick == counts.len() => skip.push(question),
+                    _ => break,
+                }
+            }
+        }
+        Ok(SubMenus::Preferences) => {
+            let k = || -> Result<String, Box<dyn Error>> {
+                let list = preference::preferences(schedule, &timetables.collect::<Vec<_>>())
+                    .iter()
+                    .join("\n");
+                let file = Input::<String>::new()
+                    .with_prompt("Filename to save it to, or nothing to only show it")
+                    .allow_empty(true)
+                    .interact()?;
+                if !file.is_empty() {
+                    File::create(file)?.write_all(list.as_bytes())?;
+                }
+                Ok(list)
+            }();
+            match k {
+                Ok(list) => feedback.push_str(&list),
+                Err(e) => feedback.push_str(&format!("Error saving preferences: {}", e)),
+            }
+        }
+        Ok(SubMenus::SaveFilters) => {
+            let k = Input::<String>::new()
+                .with_prompt("Filename")
+                .interact()
+                .map_err(|e| Box::new(e) as Box<dyn Error>)
+                .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))
+                .and_then(|f| to_writer(f, &*filters).map_err(|e| Box::new(e) as Box<dyn Error>));
+            match k {
+                Ok(_) => feedback.push_str("Saved!"),
+                Err(e) => feedback.push_str(&format!("Error saving filters: {}", e)),
+            }
+        }
+        Ok(SubMenus::LoadFilters) => {
+            let k = Input::<String>::new()
+                .with_prompt("Filename")
+                .interact()
+                .map_err(|e| Box::new(e) as Box<dyn Error>)
+                .and_then(|f| File::open(f).map_err(|e| Box::new(e) as Box<dyn Error>))
+                .and_then(|f| from_reader(f).map_err(|e| Box::new(e) as Box<dyn Error>));
+            match k {
+                Ok(f) => {
+                    feedback.push_str("Loaded!");
+                    *filters = f
+                }
+                Err(e) => feedback.push_str(&format!("Error saving filters: {}", e)),
+            }
+        }
+        Ok(SubMenus::Close) => return false,
+        Ok(SubMenus::ExportToIcal) => {
+            let k = || -> Result<(), Box<dyn Error>> {
+                let mut file = Input::<String>::new()
+                    .with_prompt("Filename")
+                    .interact()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)
+                    .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))?;
+                let time_table = match timetables.exactly_one() {
+                    Ok(t) => t,
+                    Err(_) => return Err("Either too many timetables or too few".into()),
+                };
+                let start_date = Input::<NaiveDate>::new()
+                    .with_prompt("Start date: YY-MM-DD")
+                    .interact()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)?;
+                let end_date = Input::<NaiveDate>::new()
+                    .with_prompt("End date: YY-MM-DD")
+                    .interact()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)?;
+                ical::write_cal(&mut file, time_table, start_date, end_date)
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)
+            }();
+            match k {
+                Ok(_) => feedback.push_str("Saved!"),
+                Err(e) => feedback.push_str(&format!("Error exporting to iCal: {}", e)),
+            }
+        }
+        Ok(SubMenus::ExportToJson) => {
+            let k = || -> Result<(), Box<dyn Error>> {
+                let file = Input::<String>::new()
+                    .with_prompt("Filename")
+                    .interact()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)
+                    .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))?;
+                export::write_json(file, timetables)?;
+                Ok(())
+            }();
+            match k {
+                Ok(_) => feedback.push_str("Saved!"),
+                Err(e) => feedback.push_str(&format!("Error exporting to JSON: {}", e)),
+            }
+        }
+        Ok(SubMenus::ExportToCsv) => {
+            let k = Input::<String>::new()
+                .with_prompt("Filename")
+                .interact()
+                .map_err(|e| Box::new(e) as Box<dyn Error>)
+                .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))
+                .and_then(|f| {
+                    export::write_csv(f, timetables).map_err(|e| Box::new(e) as Box<dyn Error>)
+                });
+            match k {
+                Ok(_) => feedback.push_str("Saved!"),
+                Err(e) => feedback.push_str(&format!("Error exporting to CSV: {}", e)),
+            }
+        }
+        Ok(SubMenus::ExportToSchedule) => {
+            let k = || -> Result<(), Box<dyn Error>> {
+                let time_table = match timetables.exactly_one() {
+                    Ok(t) => t,
+                    Err(_) => return Err("Either too many timetables or too few".into()),
+                };
+                let file = Input::<String>::new()
+                    .with_prompt("Filename")
+                    .interact()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)
+                    .and_then(|f| File::create(f).map_err(|e| Box::new(e) as Box<dyn Error>))?;
+                export::write_schedule(file, schedule, time_table)
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)
+            }();
+            match k {
+                Ok(_) => feedback.push_str("Saved!"),
+                Err(e) => feedback.push_str(&format!("Error exporting shifts: {}", e)),
+            }
+        }
+        Err(_) => feedback.push_str("Invalid choice"),
+    }
+    true
+}
+
+pub fn parse_time(s: &str) -> ParseResult<NaiveTime> {
+    match s {
+        _ if s.len() < 3 => NaiveTime::parse_from_str(&format!("{}:00", s), "%H:%M"),
+        _ if s.contains("h") => NaiveTime::parse_from_str(s, "%Hh%M"),
+        _ if s.contains(":") => NaiveTime::parse_from_str(s, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S")),
+        _ => NaiveTime::parse_from_str(s, "%H:%M"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times() {
+        let time = |h, m| Ok(NaiveTime::from_hms(h, m, 0));
+        assert_eq!(parse_time("9"), time(9, 0));
+        assert_eq!(parse_time("18"), time(18, 0));
+        assert_eq!(parse_time("18h30"), time(18, 30));
+        assert_eq!(parse_time("18:30"), time(18, 30));
+        assert!(parse_time("25").is_err());
+        assert!(parse_time("ab").is_err());
+        assert!(parse_time("24h00").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Ordering the shifts of each course for an enrolment form, going by how the
+//! timetables that are left are ranked.
+//!
+//! The first choice of each course is the shift in the best timetable. After
+//! it come the shifts that still fit with the first choices of every other
+//! course, so that missing one choice doesn't spoil the rest, ordered by the
+//! best timetable with each. Then the shifts that would need other courses
+//! changed too, and last the ones no timetable left has.
+
+use crate::{shifts::Shifts, timetable::TimeTable, util::ClassType};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+
+/// One shift of a [`Preference`] list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Choice {
+    pub kind: ClassType,
+    /// Where the best timetable that keeps the first choices of every other
+    /// course and has this shift is, starting at 0.
+    pub keeping_the_rest: Option<usize>,
+    /// Where the best timetable with this shift is, starting at 0.
+    pub best: Option<usize>,
+}
+
+/// The shifts of the theory or the labs of a course, most wanted first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preference<'a> {
+    pub course: &'a str,
+    pub theory: bool,
+    pub choices: Vec<Choice>,
+}
+
+/// A preference list for every course of `schedule`, from `timetables` ranked
+/// best first.
+pub fn preferences<'a>(schedule: &'a Shifts, timetables: &[&TimeTable]) -> Vec<Preference<'a>> {
+    let mut best = BTreeMap::new();
+    let mut keeping_the_rest = BTreeMap::new();
+    let first = timetables.first().map_or_else(BTreeSet::new, |t| {
+        t.chosen()
+            .iter()
+            .map(|s| (s.course.as_str(), s.kind))
+            .collect()
+    });
+    for (i, t) in timetables.iter().enumerate() {
+        let mut changed = t
+            .chosen()
+            .iter()
+            .map(|s| (s.course.as_str(), s.kind))
+            .filter(|s| !first.contains(s));
+        if let (Some(only), None) = (changed.next(), changed.next()) {
+            keeping_the_rest.entry(only).or_insert(i);
+        }
+        for s in t.chosen() {
+            best.entry((s.course.as_str(), s.kind)).or_insert(i);
+        }
+    }
+    for s in &first {
+        keeping_the_rest.insert(*s, 0);
+    }
+    schedule
+        .class_set()
+        .into_iter()
+        .map(|((course, theory), shifts)| {
+            let mut choices = shifts
+                .iter()
+                .map(|s| Choice {
+                    kind: s.kind,
+                    keeping_the_rest: keeping_the_rest.get(&(course, s.kind)).copied(),
+                    best: best.get(&(course, s.kind)).copied(),
+                })
+                .collect::<Vec<_>>();
+            choices.sort_by_key(|c| {
+                (
+                    c.keeping_the_rest.unwrap_or(usize::MAX),
+                    c.best.unwrap_or(usize::MAX),
+                )
+            });
+            Preference {
+                course,
+                theory,
+                choices,
+            }
+        })
+        .collect()
+}
+
+/// A numbered list, saying why each shift is where it is.
+impl Display for Preference<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.theory { "theory" } else { "lab" };
+        writeln!(f, "{} {}", self.course, kind)?;
+        for (i, c) in self.choices.iter().enumerate() {
+            write!(f, "  {}. {:4} ", i + 1, c.kind.code())?;
+            match (c.keeping_the_rest, c.best) {
+                (Some(0), _) => writeln!(f, "in the best timetable")?,
+                (Some(n), _) => writeln!(f, "keeps the rest, as in #{}", n + 1)?,
+                (None, Some(n)) => writeln!(f, "changes other courses, as in #{}", n + 1)?,
+                (None, None) => writeln!(f, "in no timetable left")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeping_the_rest_comes_first() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        A:T3:10h00:11h00:Wed\n\
+                        A:T4:10h00:11h00:Thu\n\
+                        B:T1:14h00:15h00:Mon\n\
+                        B:T2:14h00:15h00:Tue"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let pick = |a, b| {
+            timetables
+                .iter()
+                .find(|t| {
+                    t.has_the_shift(ClassType::T(a), "A") && t.has_the_shift(ClassType::T(b), "B")
+                })
+                .unwrap()
+        };
+        let ranked = [pick(1, 1), pick(3, 2), pick(2, 1), pick(3, 1)];
+        let lists = preferences(&schedule, &ranked);
+        assert_eq!(
+            lists[0].to_string(),
+            "A theory\n  \
+             1. T1   in the best timetable\n  \
+             2. T2   keeps the rest, as in #3\n  \
+             3. T3   keeps the rest, as in #4\n  \
+             4. T4   in no timetable left\n"
+        );
+        assert_eq!(
+            lists[1].to_string(),
+            "B theory\n  \
+             1. T1   in the best timetable\n  \
+             2. T2   changes other courses, as in #2\n"
+        );
+        assert_eq!(
+            lists[0].choices[2],
+            Choice {
+                kind: ClassType::T(3),
+                keeping_the_rest: Some(3),
+                best: Some(1),
+            }
+        );
+        assert!(preferences(&schedule, &[])[0]
+            .choices
+            .iter()
+            .all(|c| c.best.is_none()));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::timetable::TimeTable;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+/// How timetables are ordered, best first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Ranking {
+    /// Least time spent between the first and last class of each day.
+    #[default]
+    CampusTime,
+}
+
+impl Ranking {
+    /// A key that sorts better timetables first.
+    pub fn key(self, t: &TimeTable) -> isize {
+        match self {
+            Self::CampusTime => t.sum_work_hours(),
+        }
+    }
+
+    /// Sorts `timetables` best first, keeping the order of equally good ones.
+    pub fn sort<T: Borrow<TimeTable> + Send>(self, timetables: &mut [T]) {
+        #[cfg(feature = "parallel")]
+        timetables.par_sort_by_cached_key(|t| self.key(t.borrow()));
+        #[cfg(not(feature = "parallel"))]
+        timetables.sort_by_cached_key(|t| self.key(t.borrow()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_campus_time_first() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        A:T3:14h00:15h00:Mon\n\
+                        A:T4:10h00:11h00:Wed\n\
+                        B:T1:11h00:12h00:Mon"
+            .parse()
+            .unwrap();
+        let mut timetables = TimeTable::all_the_combos(&schedule);
+        let before = timetables.iter().map(TimeTable::key).collect::<Vec<_>>();
+        Ranking::default().sort(&mut timetables);
+        let spans = timetables
+            .iter()
+            .map(TimeTable::sum_work_hours)
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![2, 2, 3, 7]);
+        // T2 and T4 tie, and stay in the order they were generated in.
+        assert_eq!(timetables[0].key(), before[1]);
+        assert_eq!(timetables[1].key(), before[3]);
+    }
+}
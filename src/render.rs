@@ -0,0 +1,373 @@
+//! Drawing a week as a grid of half hours in a terminal.
+//!
+//! A [`Grid`] holds what goes in each half hour of each day, and is rendered
+//! with a column per day and a row per half hour, according to some
+//! [`RenderOptions`].
+
+use crate::{
+    theme::CourseStyle,
+    timetable::{index_to_time, SLOTS},
+    util::{WeekDay, ALL_DAYS, WEEKDAYS},
+};
+use std::{fmt::Write, ops::Range};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const TIME_WIDTH: usize = 5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Draw lines around and between the cells.
+    pub borders: bool,
+    /// Merge consecutive rows that look the same and leave less space between
+    /// columns.
+    pub compact: bool,
+    /// Show every day of the week, even those without classes.
+    pub all_days: bool,
+    /// List the courses and their colours after the grid.
+    pub legend: bool,
+    /// Maximum width of each line, names that don't fit are shortened.
+    pub width: Option<usize>,
+}
+
+/// What goes in one half hour of one day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub style: CourseStyle,
+    /// Theory classes are drawn over their colour and labs in it.
+    pub theory: bool,
+}
+
+impl Cell {
+    /// The columns left for the patterns, if the style has them, so the
+    /// course can be told apart even when the text would fill the cell.
+    fn marker_width(&self) -> usize {
+        self.style.pattern.map_or(0, |_| 1) + self.style.second_pattern.map_or(0, |_| 1)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    cells: Vec<Vec<Option<Cell>>>,
+    legend: Vec<(String, CourseStyle)>,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            cells: vec![vec![None; SLOTS]; WEEKDAYS],
+            legend: Vec::new(),
+        }
+    }
+}
+
+impl Grid {
+    pub fn set(&mut self, day: WeekDay, slot: usize, cell: Cell) {
+        self.cells[day as usize][slot] = Some(cell);
+    }
+
+    pub fn get(&self, day: WeekDay, slot: usize) -> Option<&Cell> {
+        self.cells[day as usize][slot].as_ref()
+    }
+
+    /// Adds an entry to the legend shown when [`RenderOptions::legend`] is set.
+    pub fn add_legend<S: Into<String>>(&mut self, name: S, style: CourseStyle) {
+        self.legend.push((name.into(), style));
+    }
+
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let days = self.days(options);
+        let rows = self.rows(&days);
+        self.render_within(options, &days, rows)
+    }
+
+    /// The days [`Grid::render`] shows: those with something in them, or
+    /// every day with [`RenderOptions::all_days`].
+    pub fn days(&self, options: &RenderOptions) -> Vec<WeekDay> {
+        ALL_DAYS
+            .iter()
+            .copied()
+            .filter(|d| options.all_days || self.cells[*d as usize].iter().any(Option::is_some))
+            .collect()
+    }
+
+    /// The half hours from the first to the last busy one in any of `days`.
+    pub fn rows(&self, days: &[WeekDay]) -> Range<usize> {
+        let busy = |i: usize| days.iter().any(|d| self.cells[*d as usize][i].is_some());
+        let first = (0..SLOTS).find(|i| busy(*i)).unwrap_or(0);
+        let last = (0..SLOTS).rev().find(|i| busy(*i)).map_or(0, |i| i + 1);
+        first..last
+    }
+
+    /// Renders only the given days and half hours, so that grids shown
+    /// together line up.
+    pub fn render_within(
+        &self,
+        options: &RenderOptions,
+        days: &[WeekDay],
+        rows: Range<usize>,
+    ) -> String {
+        let (first, last) = (rows.start, rows.end);
+        let (left, sep, right) = match (options.borders, options.compact) {
+            (true, _) => ("│ ", " │ ", " │"),
+            (false, true) => ("", " ", ""),
+            (false, false) => ("", "  ", ""),
+        };
+        let natural = days
+            .iter()
+            .flat_map(|d| self.cells[*d as usize][first..last].iter().flatten())
+            .map(|c| c.text.width() + c.marker_width())
+            .chain(Some(3))
+            .max()
+            .unwrap_or(3);
+        let col = match options.width {
+            Some(width) if !days.is_empty() => {
+                let fixed = left.width() + TIME_WIDTH + right.width();
+                let per_day = width.saturating_sub(fixed) / days.len();
+                natural.min(per_day.saturating_sub(sep.width()).max(3))
+            }
+            _ => natural,
+        };
+
+        let mut out = String::new();
+        let line = |l: &str, m: &str, r: &str| {
+            let bar = |w| "─".repeat(w + 2);
+            let mut s = format!("{}{}", l, bar(TIME_WIDTH));
+            for _ in days {
+                s.push_str(m);
+                s.push_str(&bar(col));
+            }
+            s.push_str(r);
+            s
+        };
+        if options.borders {
+            writeln!(out, "{}", line("┌", "┬", "┐")).unwrap();
+        }
+        write!(out, "{}{:w$}", left, "", w = TIME_WIDTH).unwrap();
+        for d in days {
+            write!(out, "{}{:w$}", sep, d.to_string(), w = col).unwrap();
+        }
+        writeln!(out, "{}", right).unwrap();
+        if options.borders {
+            writeln!(out, "{}", line("├", "┼", "┤")).unwrap();
+        }
+        let mut previous: Option<Vec<Option<&Cell>>> = None;
+        for i in first..last {
+            let row = days
+                .iter()
+                .map(|d| self.cells[*d as usize][i].as_ref())
+                .collect::<Vec<_>>();
+            if options.compact && previous.as_ref() == Some(&row) {
+                continue;
+            }
+            write!(out, "{}{}", left, index_to_time(i).format("%H:%M")).unwrap();
+            for cell in &row {
+                out.push_str(sep);
+                match cell {
+                    Some(c) => {
+                        let text = truncate(&c.text, col - c.marker_width());
+                        let padding = col - text.width();
+                        out.push_str(&c.style.paint(c.theory, text));
+                        out.push_str(&c.style.fill(padding));
+                    }
+                    None => out.extend(std::iter::repeat_n(' ', col)),
+                }
+            }
+            writeln!(out, "{}", right).unwrap();
+            previous = Some(row);
+        }
+        if options.borders {
+            writeln!(out, "{}", line("└", "┴", "┘")).unwrap();
+        }
+        if options.legend {
+            let mut width = 0;
+            for (name, style) in &self.legend {
+                let swatch = style.fill(2);
+                let entry_width = 2 + 1 + name.width() + 2;
+                if width > 0 && options.width.is_some_and(|w| width + entry_width > w) {
+                    out.push('\n');
+                    width = 0;
+                }
+                write!(out, "{} {}  ", style.paint(true, swatch), name).unwrap();
+                width += entry_width;
+            }
+            if width > 0 {
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// Shortens `s` to at most `width` columns, marking it with an ellipsis if it
+/// had to be cut.
+fn truncate(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut w = 0;
+    for c in s.chars() {
+        let cw = c.width().unwrap_or(0);
+        if w + cw + 1 > width {
+            break;
+        }
+        out.push(c);
+        w += cw;
+    }
+    out.push('…');
+    out
+}
+
+/// Puts blocks of text next to each other, `gap` columns apart.
+pub fn side_by_side<S: AsRef<str>>(blocks: &[S], gap: usize) -> String {
+    let lines = blocks
+        .iter()
+        .map(|b| b.as_ref().lines().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = lines
+        .iter()
+        .map(|l| l.iter().map(|l| visible_width(l)).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let height = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let mut out = String::new();
+    for i in 0..height {
+        let mut row = String::new();
+        for (block, width) in lines.iter().zip(&widths) {
+            let line = block.get(i).copied().unwrap_or("");
+            row.push_str(line);
+            row.extend(std::iter::repeat_n(' ', width - visible_width(line) + gap));
+        }
+        out.push_str(row.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// The width of `s` in a terminal, not counting colour escape codes.
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: CourseStyle = CourseStyle {
+        color: None,
+        pattern: None,
+        second_pattern: None,
+    };
+
+    fn grid(text: &str, pattern: Option<char>) -> Grid {
+        let mut grid = Grid::default();
+        let style = CourseStyle {
+            color: None,
+            pattern,
+            second_pattern: None,
+        };
+        let cell = Cell {
+            text: text.to_string(),
+            style,
+            theory: true,
+        };
+        grid.set(WeekDay::Mon, 20, cell);
+        grid
+    }
+
+    fn row(grid: &Grid, width: Option<usize>) -> String {
+        let options = RenderOptions {
+            width,
+            ..RenderOptions::default()
+        };
+        grid.render(&options).lines().nth(1).unwrap().to_string()
+    }
+
+    #[test]
+    fn patterns_always_show() {
+        let long = grid("Programming", Some('#'));
+        assert_eq!(row(&long, None), "10:00  Programming#");
+        assert_eq!(row(&long, Some(12)), "10:00  Pro…#");
+        assert_eq!(row(&grid("PL", Some('#')), None), "10:00  PL#");
+        assert_eq!(row(&grid("Programming", None), Some(12)), "10:00  Prog…");
+    }
+
+    #[test]
+    fn options() {
+        let mut grid = grid("PL", None);
+        grid.set(
+            WeekDay::Mon,
+            21,
+            grid.get(WeekDay::Mon, 20).unwrap().clone(),
+        );
+        grid.set(
+            WeekDay::Wed,
+            22,
+            Cell {
+                text: "AI".to_string(),
+                style: PLAIN,
+                theory: false,
+            },
+        );
+        grid.add_legend("Programming", PLAIN);
+        assert_eq!(
+            grid.render(&RenderOptions::default()),
+            "       Mon  Wed\n\
+             10:00  PL      \n\
+             10:30  PL      \n\
+             11:00       AI \n"
+        );
+        let compact = RenderOptions {
+            compact: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            grid.render(&compact),
+            "      Mon Wed\n\
+             10:00 PL     \n\
+             11:00     AI \n"
+        );
+        let borders = RenderOptions {
+            borders: true,
+            legend: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            grid.render(&borders),
+            "┌───────┬─────┬─────┐\n\
+             │       │ Mon │ Wed │\n\
+             ├───────┼─────┼─────┤\n\
+             │ 10:00 │ PL  │     │\n\
+             │ 10:30 │ PL  │     │\n\
+             │ 11:00 │     │ AI  │\n\
+             └───────┴─────┴─────┘\n   \
+             Programming  \n"
+        );
+        let all_days = RenderOptions {
+            all_days: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(grid.days(&all_days), ALL_DAYS.to_vec());
+        assert_eq!(grid.rows(&[WeekDay::Wed]), 22..23);
+        assert_eq!(Grid::default().render(&all_days).lines().count(), 1);
+    }
+
+    #[test]
+    fn blocks_side_by_side() {
+        assert_eq!(truncate("Programming", 5), "Prog…");
+        assert_eq!(visible_width("\x1b[31mPL\x1b[0m"), 2);
+        assert_eq!(
+            side_by_side(&["a\nbbb", "\x1b[31mc\x1b[0m\nd\ne"], 2),
+            "a    \x1b[31mc\x1b[0m\nbbb  d\n     e\n"
+        );
+    }
+}
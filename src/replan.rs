@@ -0,0 +1,183 @@
+//! Coping with a schedule that was republished after enrolling: what moved,
+//! what doesn't fit anymore, and the closest timetable that does.
+
+use crate::{
+    shifts::{format_times, Shifts, Times},
+    swap::{self, SwapPlan},
+    timetable::{Conflict, TimeTable, TimetableKey},
+    util::ClassType,
+};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+};
+
+/// An enrolled shift whose classes changed, `after` being `None` when it was
+/// removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Moved {
+    pub course: String,
+    pub kind: ClassType,
+    pub before: Times,
+    pub after: Option<Times>,
+}
+
+impl Display for Moved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} -> ",
+            self.course,
+            self.kind.code(),
+            format_times(&self.before)
+        )?;
+        match &self.after {
+            Some(after) => write!(f, "{}", format_times(after)),
+            None => write!(f, "removed"),
+        }
+    }
+}
+
+fn enrolled(key: &TimetableKey) -> impl Iterator<Item = (&str, ClassType)> {
+    key.iter()
+        .flat_map(|(course, kinds)| kinds.iter().map(move |k| (course.as_str(), *k)))
+}
+
+/// The shifts in `enrolment` whose classes are different in `new` than they
+/// were in `old`.
+pub fn moved(old: &Shifts, new: &Shifts, enrolment: &TimetableKey) -> Vec<Moved> {
+    enrolled(enrolment)
+        .filter_map(|(course, kind)| {
+            let before = old.shift(course, kind)?.times();
+            let after = new.shift(course, kind).map(|s| s.times());
+            (after.as_ref() != Some(&before)).then(|| Moved {
+                course: course.to_string(),
+                kind,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Every shift in `enrolment` that overlaps itself in `schedule`, once, and
+/// then every pair of the other shifts that overlap.
+pub fn conflicts(schedule: &Shifts, enrolment: &TimetableKey) -> Vec<Conflict> {
+    let (broken, shifts): (Vec<_>, Vec<_>) = enrolled(enrolment)
+        .filter_map(|(course, kind)| schedule.shift(course, kind))
+        .partition(|s| s.overlaps_itself());
+    let mut found = broken
+        .iter()
+        .filter_map(|s| TimeTable::default().add(s).err())
+        .collect::<Vec<_>>();
+    for (i, a) in shifts.iter().enumerate() {
+        let alone = TimeTable::default()
+            .add(a)
+            .expect("A shift that doesn't overlap itself fits alone");
+        found.extend(
+            shifts[i + 1..]
+                .iter()
+                .filter_map(|b| alone.clone().add(b).err()),
+        );
+    }
+    found
+}
+
+/// The timetables closest to `enrolment`, changing as few shifts as possible
+/// and then as few courses as possible.
+pub fn closest<'a>(enrolment: &TimetableKey, timetables: &[&'a TimeTable]) -> Vec<SwapPlan<'a>> {
+    let mut plans = timetables
+        .iter()
+        .map(|timetable| SwapPlan {
+            timetable,
+            swaps: swap::swaps_to(enrolment, timetable),
+        })
+        .collect::<Vec<_>>();
+    plans.sort_by_cached_key(|p| {
+        let courses = p.swaps.iter().map(|s| &s.course).collect::<BTreeSet<_>>();
+        (p.swaps.len(), courses.len())
+    });
+    plans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(shifts: &[(&str, ClassType)]) -> TimetableKey {
+        let mut key = TimetableKey::new();
+        for (course, kind) in shifts {
+            key.entry(course.to_string()).or_default().insert(*kind);
+        }
+        key
+    }
+
+    #[test]
+    fn self_overlaps_are_reported_once() {
+        let schedule = "SO:L1:10h00:12h00:Mon\n\
+                        SO:L1:11h00:13h00:Mon\n\
+                        A:T1:09h00:10h00:Tue\n\
+                        B:T1:09h30:10h30:Tue\n\
+                        C:T1:11h00:12h00:Mon"
+            .parse()
+            .unwrap();
+        let enrolment = key(&[
+            ("SO", ClassType::L(1)),
+            ("A", ClassType::T(1)),
+            ("B", ClassType::T(1)),
+            ("C", ClassType::T(1)),
+        ]);
+        let found = conflicts(&schedule, &enrolment)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                "Mon 11:00-12:00: SO L1".to_string(),
+                "Tue 09:30-10:00: A T1 and B T1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_shifts() {
+        let old = "A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nB:T1:09h00:10h00:Tue"
+            .parse()
+            .unwrap();
+        let new = "A:T1:10h00:11h00:Mon\nA:T2:14h00:15h00:Mon"
+            .parse()
+            .unwrap();
+        let enrolment = key(&[
+            ("A", ClassType::T(1)),
+            ("A", ClassType::T(2)),
+            ("B", ClassType::T(1)),
+        ]);
+        let moved = moved(&old, &new, &enrolment)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            moved,
+            vec![
+                "A T2: Mon 11:00-12:00 -> Mon 14:00-15:00".to_string(),
+                "B T1: Tue 09:00-10:00 -> removed".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn closest_changes_the_fewest_shifts() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:11h00:12h00:Mon\n\
+                        B:T1:10h00:11h00:Mon\n\
+                        B:T2:12h00:13h00:Mon"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let enrolment = key(&[("A", ClassType::T(1)), ("B", ClassType::T(1))]);
+        let plans = closest(&enrolment, &timetables.iter().collect::<Vec<_>>());
+        let swaps = plans.iter().map(|p| p.swaps.len()).collect::<Vec<_>>();
+        assert_eq!(swaps, vec![1, 1, 2]);
+    }
+}
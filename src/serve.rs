@@ -0,0 +1,329 @@
+//! A small HTTP server exposing the library over JSON, meant to back a web
+//! front-end. It only listens on localhost and handles one request at a time.
+//!
+//! Endpoints:
+//! - `POST /schedule` with a schedule file as the body replaces the schedule.
+//! - `GET /filters` and `POST /filters` read and replace the filters, in the
+//!   same format as the saved filter files.
+//! - `GET /timetables?page=0&per_page=20&ranking=CampusTime` lists a page of the
+//!   remaining timetables, best first, with at most 100 per page.
+//! - `GET /timetables/{n}` returns a single timetable of that list.
+//! - `GET /timetables/{n}/ical?start=2020-02-10&end=2020-06-01` and
+//!   `GET /timetables/{n}/html` export it.
+
+use chrono::NaiveDate;
+use scheduler::{export, ical, Ranking, Shifts, TimeTable, TimetableFilters};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Cursor},
+};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+const DEFAULT_PER_PAGE: usize = 20;
+
+const ENDPOINTS: &str = "\
+GET  /schedule
+POST /schedule
+GET  /filters
+POST /filters
+GET  /timetables?page=0&per_page=20&ranking=CampusTime
+GET  /timetables/{n}
+GET  /timetables/{n}/html
+GET  /timetables/{n}/ical?start=YYYY-MM-DD&end=YYYY-MM-DD
+";
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+#[derive(Default)]
+struct State {
+    schedule: Shifts,
+    timetables: Vec<TimeTable>,
+    filters: TimetableFilters,
+}
+
+impl State {
+    fn set_schedule(&mut self, schedule: Shifts) {
+        self.timetables = TimeTable::all_the_combos(&schedule);
+        self.schedule = schedule;
+    }
+
+    fn remaining(&self, ranking: Ranking) -> Vec<&TimeTable> {
+        let mut remaining = self.filters.apply(&self.timetables);
+        ranking.sort(&mut remaining);
+        remaining
+    }
+
+    fn summary(&self) -> serde_json::Value {
+        json!({
+            "courses": self.schedule.courses().count(),
+            "timetables": self.timetables.len(),
+            "remaining": self.filters.apply(&self.timetables).len(),
+        })
+    }
+}
+
+pub fn serve(schedule: Option<Shifts>, port: u16) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http(("127.0.0.1", port))?;
+    let mut state = State::default();
+    if let Some(s) = schedule {
+        state.set_schedule(s);
+    }
+    eprintln!("Listening on http://127.0.0.1:{}", port);
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut state, &mut request)
+            .unwrap_or_else(|e| json_response(500, &json!({ "error": e.to_string() })));
+        if let Err(e) = request.respond(response) {
+            eprintln!("Error responding: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle(state: &mut State, request: &mut Request) -> io::Result<HttpResponse> {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (request.url().to_string(), HashMap::new()),
+    };
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let response = match (request.method(), segments.as_slice()) {
+        (Method::Get, []) => text_response(200, "text/plain", ENDPOINTS),
+        (Method::Get, ["schedule"]) => json_response(200, &state.summary()),
+        (Method::Post, ["schedule"]) => {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body)?;
+            match body.parse() {
+                Ok(schedule) => {
+                    state.set_schedule(schedule);
+                    json_response(200, &state.summary())
+                }
+                Err(e) => error_response(400, e),
+            }
+        }
+        (Method::Get, ["filters"]) => json_response(200, &state.filters),
+        (Method::Post, ["filters"]) => match serde_json::from_reader(request.as_reader()) {
+            Ok(filters) => {
+                state.filters = filters;
+                json_response(200, &state.summary())
+            }
+            Err(e) => error_response(400, e),
+        },
+        (Method::Get, ["timetables", rest @ ..]) => {
+            let ranking = match query.get("ranking").map(|r| parse_ranking(r)) {
+                Some(Ok(r)) => r,
+                Some(Err(e)) => return Ok(error_response(400, e)),
+                None => Ranking::default(),
+            };
+            let remaining = state.remaining(ranking);
+            match rest {
+                [] => {
+                    let number =
+                        |k, default| query.get(k).map_or(Some(default), |n| n.parse().ok());
+                    let (page, per_page) =
+                        match (number("page", 0), number("per_page", DEFAULT_PER_PAGE)) {
+                            (Some(page), Some(per_page))
+                                if (1..=export::MAX_PER_PAGE).contains(&per_page) =>
+                            {
+                                (page, per_page)
+                            }
+                            _ => {
+                                return Ok(error_response(
+                                    400,
+                                    format!(
+                                "Expected a page number and between 1 and {} timetables per page",
+                                export::MAX_PER_PAGE
+                            ),
+                                ))
+                            }
+                        };
+                    let mut body = Vec::new();
+                    export::write_json_page(&mut body, &remaining, page, per_page)?;
+                    text_response(200, "application/json", body)
+                }
+                [n, format @ ..] => {
+                    let timetable = match n.parse::<usize>().ok().and_then(|n| remaining.get(n)) {
+                        Some(t) => *t,
+                        None => return Ok(error_response(404, "No such timetable")),
+                    };
+                    let mut body = Vec::new();
+                    match format {
+                        [] => {
+                            export::write_json(&mut body, Some(timetable))?;
+                            text_response(200, "application/json", body)
+                        }
+                        ["html"] => {
+                            export::write_html(&mut body, timetable)?;
+                            text_response(200, "text/html; charset=utf-8", body)
+                        }
+                        ["ical"] => {
+                            let date = |k| {
+                                query
+                                    .get(k)
+                                    .and_then(|d: &String| d.parse::<NaiveDate>().ok())
+                            };
+                            match (date("start"), date("end")) {
+                                (Some(start), Some(end)) => {
+                                    ical::write_cal(&mut body, timetable, start, end)?;
+                                    text_response(200, "text/calendar", body)
+                                }
+                                _ => error_response(400, "Expected start and end dates"),
+                            }
+                        }
+                        _ => error_response(404, "Not found"),
+                    }
+                }
+            }
+        }
+        _ => error_response(404, "Not found"),
+    };
+    Ok(response)
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (url_decode(k), url_decode(v)))
+        .collect()
+}
+
+/// Undoes the `%XX` escapes and `+` for spaces of a query string, leaving
+/// invalid escapes as they are.
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_ranking(s: &str) -> serde_json::Result<Ranking> {
+    serde_json::from_value(serde_json::Value::String(s.to_string()))
+}
+
+fn text_response<B: Into<Vec<u8>>>(status: u16, content_type: &str, body: B) -> HttpResponse {
+    Response::from_data(body.into())
+        .with_status_code(StatusCode(status))
+        .with_header(
+            Header::from_bytes("Content-Type", content_type).expect("Content type is ascii"),
+        )
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> HttpResponse {
+    text_response(
+        status,
+        "application/json",
+        serde_json::to_vec(body).expect("Serializing to memory doesn't fail"),
+    )
+}
+
+fn error_response<E: ToString>(status: u16, e: E) -> HttpResponse {
+    json_response(status, &json!({ "error": e.to_string() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    const SCHEDULE: &str = "A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Mon\nA:T3:12h00:13h00:Fri";
+
+    fn request(state: &mut State, method: Method, path: &str, body: &'static str) -> (u16, String) {
+        let mut request = TestRequest::new()
+            .with_method(method)
+            .with_path(path)
+            .with_body(body)
+            .into();
+        let response = handle(state, &mut request).unwrap();
+        let status = response.status_code().0;
+        (
+            status,
+            String::from_utf8(response.into_reader().into_inner()).unwrap(),
+        )
+    }
+
+    fn get(state: &mut State, path: &str) -> (u16, serde_json::Value) {
+        let (status, body) = request(state, Method::Get, path, "");
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn schedule_filters_and_pages() {
+        let mut state = State::default();
+        let (status, _) = request(&mut state, Method::Post, "/schedule", SCHEDULE);
+        assert_eq!(status, 200);
+        assert_eq!(get(&mut state, "/schedule").1["timetables"], 3);
+        let (status, _) = request(
+            &mut state,
+            Method::Post,
+            "/filters",
+            r#"{"starts_after":null,"ends_before":null,"free_days":["Fri"],"has_the_shift":[],"hasnt_the_shift":[]}"#,
+        );
+        assert_eq!(status, 200);
+        let (status, page) = get(&mut state, "/timetables?page=1&per_page=1");
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["timetables"].as_array().unwrap().len(), 1);
+        let (status, one) = get(&mut state, "/timetables/0");
+        assert_eq!(status, 200);
+        assert_eq!(one["timetables"][0]["shifts"][0]["course"], "A");
+        let (status, html) = request(&mut state, Method::Get, "/timetables/0/html", "");
+        assert_eq!(status, 200);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn bad_requests() {
+        let mut state = State::default();
+        request(&mut state, Method::Post, "/schedule", SCHEDULE);
+        assert_eq!(
+            request(&mut state, Method::Post, "/schedule", "A:T1").0,
+            400
+        );
+        assert_eq!(get(&mut state, "/timetables?ranking=Nope").0, 400);
+        assert_eq!(get(&mut state, "/timetables/9").0, 404);
+        assert_eq!(get(&mut state, "/timetables/0/ical").0, 400);
+        assert_eq!(get(&mut state, "/nowhere").0, 404);
+        for query in &["per_page=0", "per_page=1000", "page=-1", "page=x"] {
+            let (status, body) = get(&mut state, &format!("/timetables?{}", query));
+            assert_eq!(status, 400, "{}", query);
+            assert!(body["error"].is_string());
+        }
+        let (status, page) = get(&mut state, &format!("/timetables?page={}", usize::MAX));
+        assert_eq!(status, 200);
+        assert!(page["timetables"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn query_values_are_decoded() {
+        let query = parse_query("ranking=Campus%54ime&per_page=%32%30&a+b=c%2Bd&bad=%zz");
+        assert_eq!(query["ranking"], "CampusTime");
+        assert_eq!(query["per_page"], "20");
+        assert_eq!(query["a b"], "c+d");
+        assert_eq!(query["bad"], "%zz");
+    }
+}
@@ -0,0 +1,172 @@
+//! Everything needed to pick up where a previous run left off.
+//!
+//! Sessions are saved as JSON, and remember which schedule they were made for
+//! by its path and a hash of its contents, so that resuming after the schedule
+//! was edited can be noticed.
+
+use crate::{filters::TimetableFilters, rank::Ranking, timetable::TimetableKey};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSession {
+    pub schedule: Option<PathBuf>,
+    /// [`content_hash`] of the schedule when the session was saved.
+    pub schedule_hash: Option<String>,
+    pub filters: TimetableFilters,
+    pub ranking: Ranking,
+    /// The timetable that was being looked at.
+    pub selected: Option<TimetableKey>,
+    /// Called `liked` in older sessions, which only had the shifts.
+    #[serde(alias = "liked")]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// A timetable worth coming back to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SavedBookmark")]
+pub struct Bookmark {
+    pub shifts: TimetableKey,
+    pub note: String,
+}
+
+/// A [`Bookmark`] as saved now, or a liked timetable of an older session.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedBookmark {
+    Bookmark {
+        shifts: TimetableKey,
+        #[serde(default)]
+        note: String,
+    },
+    Liked(TimetableKey),
+}
+
+impl From<SavedBookmark> for Bookmark {
+    fn from(saved: SavedBookmark) -> Self {
+        match saved {
+            SavedBookmark::Bookmark { shifts, note } => Self { shifts, note },
+            SavedBookmark::Liked(shifts) => Self {
+                shifts,
+                note: String::new(),
+            },
+        }
+    }
+}
+
+impl SavedSession {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Writes the session to a temporary file next to `path` and then moves it
+    /// over, so that a crash while saving doesn't lose the previous one.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        serde_json::to_writer_pretty(File::create(&tmp)?, self)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Whether the schedule with these contents isn't the one the session was
+    /// saved with.
+    pub fn schedule_changed(&self, contents: &[u8]) -> bool {
+        self.schedule_hash
+            .as_ref()
+            .is_some_and(|h| *h != content_hash(contents))
+    }
+}
+
+/// A hash of a file's contents that stays the same across runs and platforms
+/// (64 bit FNV-1a, in hex).
+pub fn content_hash(contents: &[u8]) -> String {
+    let hash = contents.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClassType, WeekDay};
+
+    fn key() -> TimetableKey {
+        let mut key = TimetableKey::new();
+        key.entry("PL".to_string())
+            .or_default()
+            .insert(ClassType::T(1));
+        key
+    }
+
+    #[test]
+    fn bookmarks_round_trip() {
+        let session = SavedSession {
+            bookmarks: vec![Bookmark {
+                shifts: key(),
+                note: "free fridays".to_string(),
+            }],
+            ..SavedSession::default()
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SavedSession>(&json).unwrap(),
+            session
+        );
+    }
+
+    #[test]
+    fn old_likes_are_bookmarks() {
+        let json = serde_json::json!({ "liked": [key()] });
+        let session = serde_json::from_value::<SavedSession>(json).unwrap();
+        assert_eq!(
+            session.bookmarks,
+            vec![Bookmark {
+                shifts: key(),
+                note: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn hashes() {
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        let session = SavedSession {
+            schedule_hash: Some(content_hash(b"A:T1:10h00:11h00:Mon")),
+            ..SavedSession::default()
+        };
+        assert!(!session.schedule_changed(b"A:T1:10h00:11h00:Mon"));
+        assert!(session.schedule_changed(b"A:T1:10h00:11h00:Tue"));
+        assert!(!SavedSession::default().schedule_changed(b""));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("scheduler-session-{}", std::process::id()));
+        let path = dir.join("nested").join("session.json");
+        assert!(SavedSession::load(&path).is_err());
+        let mut session = SavedSession {
+            schedule: Some("schedule.txt".into()),
+            schedule_hash: Some(content_hash(b"A:T1:10h00:11h00:Mon")),
+            selected: Some(key()),
+            ..SavedSession::default()
+        };
+        session.filters.free_days.insert(WeekDay::Fri);
+        session.save(&path).unwrap();
+        assert_eq!(SavedSession::load(&path).unwrap(), session);
+        // Saving again replaces it and leaves no temporary file behind.
+        session.selected = None;
+        session.save(&path).unwrap();
+        assert_eq!(SavedSession::load(&path).unwrap(), session);
+        assert!(!path.with_extension("tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
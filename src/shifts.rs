@@ -1,31 +1,108 @@
 use crate::{
     error::ErrMessage,
+    timetable::slot_mask,
     util::{Class, ClassType, WeekDay, WEEKDAYS},
 };
-use chrono::NaiveTime;
+use chrono::{NaiveTime, Timelike};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+    sync::Arc,
 };
 
 const TIME_FORMAT: &str = "%Hh%M";
 
-type Day = HashMap<(NaiveTime, NaiveTime), Vec<Class>>;
+/// When the classes of a shift happen, by day and start.
+pub type Times = Vec<(WeekDay, NaiveTime, NaiveTime)>;
 
-pub type ClassGroup<'a> = Vec<&'a Class>;
+/// `times` like `Mon 10:00-12:00, Wed 14:00-15:00`.
+pub fn format_times(times: &[(WeekDay, NaiveTime, NaiveTime)]) -> String {
+    times
+        .iter()
+        .map(|(d, s, e)| format!("{} {}-{}", d, s.format("%H:%M"), e.format("%H:%M")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every class of one shift of a course, which are always attended together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shift {
+    pub course: String,
+    pub kind: ClassType,
+    pub classes: Vec<Class>,
+    occupancy: [u64; WEEKDAYS],
+    overlaps_itself: bool,
+}
 
-#[derive(Default, Debug)]
+impl Shift {
+    /// Groups `classes`, which have to be non empty and of the same course and
+    /// shift. A class written twice is only kept once, so it doesn't make the
+    /// shift overlap itself.
+    pub fn new(mut classes: Vec<Class>) -> Self {
+        let mut seen = BTreeSet::new();
+        classes.retain(|c| seen.insert((c.weekday, c.start, c.end)));
+        let mut occupancy = [0; WEEKDAYS];
+        let mut overlaps_itself = false;
+        for c in &classes {
+            let mask = slot_mask(c.start, c.end);
+            overlaps_itself |= occupancy[c.weekday as usize] & mask != 0;
+            occupancy[c.weekday as usize] |= mask;
+        }
+        Self {
+            course: classes[0].name.clone(),
+            kind: classes[0].kind,
+            classes,
+            occupancy,
+            overlaps_itself,
+        }
+    }
+
+    /// The half hours this shift takes up, one bit per half hour of each day.
+    pub fn occupancy(&self) -> &[u64; WEEKDAYS] {
+        &self.occupancy
+    }
+
+    /// When the classes of this shift happen, by day and start.
+    pub fn times(&self) -> Times {
+        let mut times = self
+            .classes
+            .iter()
+            .map(|c| (c.weekday, c.start, c.end))
+            .collect::<Vec<_>>();
+        times.sort();
+        times
+    }
+
+    /// Whether two classes of this shift happen at the same time, which makes it
+    /// impossible to attend.
+    pub fn overlaps_itself(&self) -> bool {
+        self.overlaps_itself
+    }
+}
+
+/// For each course, and whether it's theory or lab, the shifts that can be
+/// picked for it. Both are sorted so that everything derived from them comes
+/// out in the same order on every run.
+pub type ClassSet<'a> = BTreeMap<(&'a str, bool), Vec<Arc<Shift>>>;
+
+#[derive(Default, Debug, Clone)]
 pub struct Shifts {
-    table: [Day; WEEKDAYS],
-    classes: HashMap<String, HashSet<ClassType>>,
+    classes: Vec<Class>,
+    shifts: BTreeMap<(String, bool), BTreeMap<ClassType, Arc<Shift>>>,
 }
 
 impl Shifts {
-    pub fn parse_schedule<'a, L: Iterator<Item = String>>(l: L) -> Result<Self, ErrMessage> {
+    pub fn parse_schedule<L: Iterator<Item = String>>(l: L) -> Result<Self, ErrMessage> {
         l.enumerate()
-            .map(|(i, l)| -> Result<(WeekDay, Class), ErrMessage> {
-                // CPD:L13:08h00:09h30:Mon
-                // 0  :1  :2    :3    :4
+            .map(|(i, l)| (i + 1, l))
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| -> Result<Class, ErrMessage> {
+                // CPD:L13:08h00:09h30:Mon[:Teacher[:Room[:Year]]]
+                // 0  :1  :2    :3    :4   :5       :6    :7
                 let fields = l.split(':').map(|x| x.trim()).collect::<Vec<_>>();
+                if fields.len() < 5 {
+                    return Err(ErrMessage::new("Expected at least 5 fields", i, l));
+                }
                 let kind = match fields[1].parse() {
                     Ok(k) => k,
                     Err(e) => return Err(ErrMessage::new(e, i, l)),
@@ -38,48 +115,213 @@ impl Shifts {
                     Ok(e) => e,
                     Err(_) => return Err(ErrMessage::new("Invalid end time", i, l)),
                 };
+                if [start, end].iter().any(|t| t.minute() % 30 != 0) {
+                    return Err(ErrMessage::new(
+                        "Classes have to start and end on the hour or half hour",
+                        i,
+                        l,
+                    ));
+                }
                 let weekday = match fields[4].parse() {
                     Ok(w) => w,
                     Err(_) => return Err(ErrMessage::new("Invalid time", i, l)),
                 };
-                Ok((
+                let column = |n: usize| {
+                    fields
+                        .get(n)
+                        .filter(|f| !f.is_empty())
+                        .map(|f| f.to_string())
+                };
+                // Other tools may have put anything past the fifth column, so a
+                // year that isn't a number is left out rather than an error.
+                let year = column(7).and_then(|y| y.parse().ok());
+                Ok(Class {
                     weekday,
-                    Class {
-                        weekday,
-                        kind,
-                        start,
-                        end,
-                        name: fields[0].to_string(),
-                    },
-                ))
+                    kind,
+                    start,
+                    end,
+                    name: fields[0].to_string(),
+                    teacher: column(5),
+                    room: column(6),
+                    year,
+                    line_no: i,
+                    line: l,
+                })
             })
-            .try_fold(Self::default(), |mut acc, s| -> Result<Self, ErrMessage> {
-                let (day, c) = s?;
-                acc.classes
-                    .entry(c.name.clone())
-                    .or_default()
-                    .insert(c.kind);
-                acc.table[day as usize]
-                    .entry((c.start, c.end))
-                    .or_default()
-                    .push(c);
-                Ok(acc)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::from_classes)
+    }
+
+    fn from_classes(classes: Vec<Class>) -> Self {
+        let mut groups = BTreeMap::<_, BTreeMap<_, Vec<Class>>>::new();
+        for c in &classes {
+            groups
+                .entry((c.name.clone(), c.kind.unique_id() > 0))
+                .or_default()
+                .entry(c.kind)
+                .or_default()
+                .push(c.clone());
+        }
+        let shifts = groups
+            .into_iter()
+            .map(|(k, shifts)| {
+                let shifts = shifts
+                    .into_iter()
+                    .map(|(n, classes)| (n, Arc::new(Shift::new(classes))))
+                    .collect();
+                (k, shifts)
             })
+            .collect();
+        Self { classes, shifts }
+    }
+
+    /// The names of the courses in this schedule.
+    pub fn courses(&self) -> impl Iterator<Item = &str> {
+        let mut courses = self
+            .shifts
+            .keys()
+            .map(|(c, _)| c.as_str())
+            .collect::<Vec<_>>();
+        courses.dedup();
+        courses.into_iter()
+    }
+
+    /// Every class in the schedule, in the order they were read.
+    pub fn classes(&self) -> &[Class] {
+        &self.classes
     }
 
-    pub fn class_set(&self) -> HashMap<(&str, bool), HashMap<i64, ClassGroup>> {
-        self.table
+    /// Every shift in the schedule.
+    pub fn shifts(&self) -> impl Iterator<Item = &Arc<Shift>> {
+        self.shifts.values().flat_map(|s| s.values())
+    }
+
+    /// The same schedule with only the shifts `keep` is true for.
+    pub fn retain_shifts<F: FnMut(&Shift) -> bool>(&self, mut keep: F) -> Self {
+        let kept = self
+            .shifts()
+            .filter(|s| keep(s))
+            .map(|s| (s.course.as_str(), s.kind))
+            .collect::<BTreeSet<_>>();
+        Self::from_classes(
+            self.classes
+                .iter()
+                .filter(|c| kept.contains(&(c.name.as_str(), c.kind)))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The shift `kind` of `course`, if there is one.
+    pub fn shift(&self, course: &str, kind: ClassType) -> Option<&Arc<Shift>> {
+        self.shifts
+            .get(&(course.to_string(), kind.unique_id() > 0))?
+            .get(&kind)
+    }
+
+    pub fn class_set(&self) -> ClassSet<'_> {
+        self.shifts
             .iter()
-            .flat_map(|x| x.values())
-            .flatten()
-            .fold(HashMap::new(), |mut acc, c| {
-                let n = c.kind.unique_id();
-                acc.entry((&c.name, n > 0))
-                    .or_default()
-                    .entry(n)
-                    .or_default()
-                    .push(c);
-                acc
+            .map(|((c, theory), shifts)| {
+                ((c.as_str(), *theory), shifts.values().cloned().collect())
             })
+            .collect()
+    }
+}
+
+impl FromStr for Shifts {
+    type Err = ErrMessage;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_schedule(s.lines().map(String::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_output_whatever_the_line_order() {
+        let lines = [
+            "PL:T1:10h00:12h00:Mon",
+            "CPD:T1:11h00:13h00:Tue",
+            "PL:L1:08h00:10h00:Mon",
+            "CPD:L2:14h00:16h00:Wed",
+            "PL:L2:14h00:16h00:Fri",
+            "CPD:L1:08h00:09h00:Thu",
+        ];
+        let export = |lines: &[&str]| {
+            let schedule: Shifts = lines.join("\n").parse().unwrap();
+            let timetables = crate::TimeTable::all_the_combos(&schedule);
+            let mut out = Vec::new();
+            crate::export::write_json(&mut out, &timetables).unwrap();
+            (
+                schedule.courses().map(String::from).collect::<Vec<_>>(),
+                out,
+            )
+        };
+        let (courses, out) = export(&lines);
+        assert_eq!(courses, vec!["CPD", "PL"]);
+        let mut reversed = lines;
+        reversed.reverse();
+        assert_eq!(export(&reversed), (courses, out));
+    }
+
+    #[test]
+    fn extra_columns_are_optional() {
+        let schedule: Shifts = "H:T1:18h00:19h00:Mon:extra:stuff:from:other:tool\n\
+                                H:T1:18h00:19h00:Mon:Smith:R1:x\n\
+                                H:L1:19h00:20h00:Tue:Jones:R2:2"
+            .parse()
+            .unwrap();
+        let classes = schedule.classes();
+        assert_eq!(classes[0].teacher.as_deref(), Some("extra"));
+        assert_eq!(classes[0].room.as_deref(), Some("stuff"));
+        assert_eq!(classes[0].year, None);
+        assert_eq!(classes[0].invalid_year(), Some("from"));
+        assert_eq!(classes[1].teacher.as_deref(), Some("Smith"));
+        assert_eq!(classes[1].year, None);
+        assert_eq!(classes[1].invalid_year(), Some("x"));
+        assert_eq!(classes[2].year, Some(2));
+        assert_eq!(classes[2].invalid_year(), None);
+    }
+
+    #[test]
+    fn duplicate_lines_dont_overlap() {
+        let schedule: Shifts = "A:T1:10h00:11h00:Mon\n\
+                                A:T1:10h00:11h00:Mon\n\
+                                A:T2:10h00:11h00:Tue\n\
+                                A:T2:10h30:11h30:Tue"
+            .parse()
+            .unwrap();
+        let t1 = schedule.shift("A", ClassType::T(1)).unwrap();
+        assert!(!t1.overlaps_itself());
+        assert_eq!(t1.classes.len(), 1);
+        assert!(schedule
+            .shift("A", ClassType::T(2))
+            .unwrap()
+            .overlaps_itself());
+        assert_eq!(crate::TimeTable::all_the_combos(&schedule).len(), 1);
+    }
+
+    #[test]
+    fn times() {
+        let schedule: Shifts = "A:T1:14h00:15h00:Wed\nA:T1:10h00:12h00:Mon"
+            .parse()
+            .unwrap();
+        let times = schedule.shift("A", ClassType::T(1)).unwrap().times();
+        assert_eq!(format_times(&times), "Mon 10:00-12:00, Wed 14:00-15:00");
+    }
+
+    #[test]
+    fn bad_lines_are_errors() {
+        for line in &[
+            "H:T1:18h00:19h00",
+            "H:X1:18h00:19h00:Mon",
+            "H:T1:18h15:19h00:Mon",
+            "H:T1:18h00:19h00:Someday",
+        ] {
+            assert!(line.parse::<Shifts>().is_err(), "{}", line);
+        }
     }
 }
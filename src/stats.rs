@@ -0,0 +1,212 @@
+//! What the remaining timetables have in common: how often each shift is picked
+//! and how often each half hour is busy.
+
+use crate::{
+    render::{Cell, Grid},
+    shifts::Shifts,
+    theme::CourseStyle,
+    timetable::{TimeTable, SLOTS},
+    util::{ClassType, ALL_DAYS, WEEKDAYS},
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+/// Shades for the heatmap, from rarely to almost always busy.
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+#[derive(Debug, Clone)]
+pub struct Statistics<'a> {
+    pub timetables: usize,
+    /// How many timetables pick each shift of the schedule.
+    pub usage: BTreeMap<(&'a str, ClassType), usize>,
+    /// How many timetables have a class in each half hour of each day.
+    pub heatmap: [[usize; SLOTS]; WEEKDAYS],
+}
+
+/// Counts for part of the timetables, added up at the end.
+struct Tally<'a> {
+    usage: BTreeMap<(&'a str, ClassType), usize>,
+    heatmap: [[usize; SLOTS]; WEEKDAYS],
+}
+
+impl<'a> Tally<'a> {
+    fn new() -> Self {
+        Self {
+            usage: BTreeMap::new(),
+            heatmap: [[0; SLOTS]; WEEKDAYS],
+        }
+    }
+
+    fn add(mut self, t: &'a TimeTable) -> Self {
+        for s in t.chosen() {
+            *self.usage.entry((s.course.as_str(), s.kind)).or_default() += 1;
+        }
+        for (count, day) in self.heatmap.iter_mut().zip(t.occupancy()) {
+            for (i, c) in count.iter_mut().enumerate() {
+                *c += (day >> i & 1) as usize;
+            }
+        }
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    fn merge(mut self, other: Self) -> Self {
+        for (k, n) in other.usage {
+            *self.usage.entry(k).or_default() += n;
+        }
+        for (a, b) in self.heatmap.iter_mut().zip(&other.heatmap) {
+            a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+        }
+        self
+    }
+}
+
+impl<'a> Statistics<'a> {
+    /// Counts over `timetables`, which should have been made from `schedule`.
+    pub fn new(schedule: &'a Shifts, timetables: &[&TimeTable]) -> Self {
+        #[cfg(feature = "parallel")]
+        let tally = timetables
+            .par_iter()
+            .fold(Tally::new, |acc, t| acc.add(t))
+            .reduce(Tally::new, Tally::merge);
+        #[cfg(not(feature = "parallel"))]
+        let tally = timetables.iter().fold(Tally::new(), |acc, t| acc.add(t));
+        let usage = schedule
+            .shifts()
+            .map(|s| {
+                let key = (s.course.as_str(), s.kind);
+                (key, tally.usage.get(&key).copied().unwrap_or(0))
+            })
+            .collect();
+        Self {
+            timetables: timetables.len(),
+            usage,
+            heatmap: tally.heatmap,
+        }
+    }
+
+    /// The shifts every timetable picks.
+    pub fn forced(&self) -> impl Iterator<Item = (&'a str, ClassType)> + '_ {
+        self.usage
+            .iter()
+            .filter(move |(_, n)| **n == self.timetables && self.timetables > 0)
+            .map(|(k, _)| *k)
+    }
+
+    /// The shifts no timetable picks.
+    pub fn impossible(&self) -> impl Iterator<Item = (&'a str, ClassType)> + '_ {
+        self.usage.iter().filter(|(_, n)| **n == 0).map(|(k, _)| *k)
+    }
+
+    /// How often each half hour is busy, as a percentage next to a shade of
+    /// how high it is.
+    pub fn heatmap_grid(&self) -> Grid {
+        let shade = |pattern| CourseStyle {
+            color: None,
+            pattern: Some(pattern),
+            second_pattern: None,
+        };
+        let mut grid = Grid::default();
+        for day in ALL_DAYS.iter().copied() {
+            for (slot, n) in self.heatmap[day as usize].iter().enumerate() {
+                if *n == 0 {
+                    continue;
+                }
+                let percent = n * 100 / self.timetables;
+                let level = (percent * SHADES.len() / 100).min(SHADES.len() - 1);
+                grid.set(
+                    day,
+                    slot,
+                    Cell {
+                        text: format!("{0}{0}{1:4}%", SHADES[level], percent),
+                        style: shade(SHADES[level]),
+                        theory: true,
+                    },
+                );
+            }
+        }
+        let step = 100 / SHADES.len();
+        for (i, s) in SHADES.iter().enumerate() {
+            grid.add_legend(format!("{}-{}%", i * step, (i + 1) * step), shade(*s));
+        }
+        grid
+    }
+}
+
+/// How many timetables pick each shift, grouped by course.
+impl Display for Statistics<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Out of {} timetables:", self.timetables)?;
+        let mut course = None;
+        for ((c, kind), n) in &self.usage {
+            if course != Some(c) {
+                writeln!(f, "{}", c)?;
+                course = Some(c);
+            }
+            let percent = (n * 100).checked_div(self.timetables).unwrap_or(0);
+            write!(f, "  {:4} {:7} {:3}%", kind.code(), n, percent)?;
+            match *n {
+                0 => writeln!(f, "  impossible")?,
+                n if n == self.timetables => writeln!(f, "  forced")?,
+                _ => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::WeekDay;
+
+    #[test]
+    fn counts() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        B:T1:10h00:11h00:Wed\n\
+                        B:T2:14h00:15h00:Wed\n\
+                        C:T1:10h00:11h00:Mon"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let stats = Statistics::new(&schedule, &timetables.iter().collect::<Vec<_>>());
+        assert_eq!(stats.timetables, 2);
+        assert_eq!(stats.usage[&("A", ClassType::T(2))], 2);
+        assert_eq!(stats.usage[&("B", ClassType::T(1))], 1);
+        assert_eq!(
+            stats.forced().collect::<Vec<_>>(),
+            vec![("A", ClassType::T(2)), ("C", ClassType::T(1))]
+        );
+        assert_eq!(
+            stats.impossible().collect::<Vec<_>>(),
+            vec![("A", ClassType::T(1))]
+        );
+        assert_eq!(stats.heatmap[WeekDay::Tue as usize][20], 2);
+        assert_eq!(stats.heatmap[WeekDay::Wed as usize][28], 1);
+
+        let grid = stats.heatmap_grid();
+        assert_eq!(grid.get(WeekDay::Tue, 20).unwrap().text, "██ 100%");
+        assert_eq!(grid.get(WeekDay::Wed, 20).unwrap().text, "▓▓  50%");
+        assert_eq!(grid.get(WeekDay::Thu, 20), None);
+
+        let text = stats.to_string();
+        assert!(text.starts_with("Out of 2 timetables:\nA\n"));
+        assert!(text.contains("  T1         0   0%  impossible\n"));
+        assert!(text.contains("  T2         2 100%  forced\n"));
+        assert!(text.contains("B\n  T1         1  50%\n"));
+    }
+
+    #[test]
+    fn no_timetables() {
+        let schedule = "A:T1:10h00:11h00:Mon".parse().unwrap();
+        let stats = Statistics::new(&schedule, &[]);
+        assert_eq!(stats.forced().count(), 0);
+        assert_eq!(stats.impossible().count(), 1);
+        assert!(stats.to_string().contains("0   0%  impossible"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Finding which shifts to swap with classmates to get from the timetable
+//! someone is enrolled in to one they'd rather have.
+
+use crate::{
+    filters::TimetableFilters,
+    timetable::{TimeTable, TimetableKey},
+    util::ClassType,
+};
+use std::fmt::{self, Display};
+
+/// Changing from one shift of a course to another of the same kind, or taking
+/// one up when not enrolled in any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub course: String,
+    pub from: Option<ClassType>,
+    pub to: ClassType,
+}
+
+impl Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.from {
+            Some(from) => write!(f, "{} {} -> {}", self.course, from.code(), self.to.code()),
+            None => write!(f, "{} -> {}", self.course, self.to.code()),
+        }
+    }
+}
+
+/// A timetable that can be reached with `swaps`.
+#[derive(Debug, Clone)]
+pub struct SwapPlan<'a> {
+    pub timetable: &'a TimeTable,
+    pub swaps: Vec<Swap>,
+}
+
+/// The swaps needed to go from the shifts in `current` to `timetable`.
+pub fn swaps_to(current: &TimetableKey, timetable: &TimeTable) -> Vec<Swap> {
+    let same_kind = |a: ClassType, b: ClassType| (a.unique_id() > 0) == (b.unique_id() > 0);
+    timetable
+        .shifts()
+        .into_iter()
+        .flat_map(|(course, kinds)| kinds.into_iter().map(move |k| (course, k)))
+        .filter_map(|(course, to)| {
+            let from = current
+                .get(course)
+                .and_then(|kinds| kinds.iter().copied().find(|k| same_kind(*k, to)));
+            (from != Some(to)).then(|| Swap {
+                course: course.to_string(),
+                from,
+                to,
+            })
+        })
+        .collect()
+}
+
+/// Up to `limit` of the timetables that pass `target` that are reached with as
+/// few swaps from `current` as possible, fewest first. One is left out when it
+/// needs every swap of another and then some, as those extra swaps aren't
+/// needed.
+///
+/// Each timetable is only checked against the plans kept so far, so this takes
+/// `limit` times as long as going through the timetables once.
+pub fn find_swaps<'a>(
+    current: &TimetableKey,
+    target: &TimetableFilters,
+    timetables: &'a [TimeTable],
+    limit: usize,
+) -> Vec<SwapPlan<'a>> {
+    let mut plans = target
+        .apply(timetables)
+        .into_iter()
+        .map(|timetable| SwapPlan {
+            timetable,
+            swaps: swaps_to(current, timetable),
+        })
+        .collect::<Vec<_>>();
+    plans.sort_by_key(|p| p.swaps.len());
+    let mut minimal: Vec<SwapPlan> = Vec::new();
+    for plan in plans {
+        if minimal.len() == limit {
+            break;
+        }
+        let needless = minimal
+            .iter()
+            .any(|m| m.swaps.iter().all(|s| plan.swaps.contains(s)));
+        if !needless {
+            minimal.push(plan);
+        }
+    }
+    minimal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::WeekDay;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn fewest_swaps() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        A:L1:10h00:11h00:Wed\n\
+                        A:L2:10h00:11h00:Thu\n\
+                        B:T1:10h00:11h00:Fri"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let mut current = TimetableKey::new();
+        current.insert(
+            "A".to_string(),
+            BTreeSet::from([ClassType::T(1), ClassType::L(1)]),
+        );
+        let mut target = TimetableFilters::default();
+        target.free_days.insert(WeekDay::Mon);
+        let plans = find_swaps(&current, &target, &timetables, 5);
+        // Also moving to L2 would be a needless swap.
+        assert_eq!(plans.len(), 1);
+        let swaps = plans[0]
+            .swaps
+            .iter()
+            .map(Swap::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(swaps, vec!["A T1 -> T2", "B -> T1"]);
+        assert_eq!(swaps_to(&current, &timetables[0]).len(), 1);
+        assert_eq!(
+            swaps_to(&plans[0].timetable.key(), plans[0].timetable),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn up_to_the_limit() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        A:T3:10h00:11h00:Wed"
+            .parse()
+            .unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let mut current = TimetableKey::new();
+        current.insert("A".to_string(), BTreeSet::from([ClassType::T(1)]));
+        let mut target = TimetableFilters::default();
+        target.free_days.insert(WeekDay::Mon);
+        assert_eq!(find_swaps(&current, &target, &timetables, 5).len(), 2);
+        let first = find_swaps(&current, &target, &timetables, 1);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].swaps[0].to_string(), "A T1 -> T2");
+        assert!(find_swaps(&current, &target, &timetables, 0).is_empty());
+    }
+}
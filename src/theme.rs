@@ -0,0 +1,323 @@
+//! How courses are told apart when rendering a timetable in a terminal.
+//!
+//! Each course gets a colour from the theme's [`Palette`], unless the user picked
+//! one for it. Palettes with few colours fall back to filling the cells with a
+//! pattern once they run out, and then with two patterns taking turns, so many
+//! courses can be shown. With [`Palette::None`] no escape codes are written at
+//! all and every course gets a pattern instead.
+//!
+//! Themes can be loaded from JSON files like:
+//!
+//! ```text
+//! { "palette": "ColorBlind", "courses": { "PL": "#ff8800", "CPD": "blue", "SO": "208" } }
+//! ```
+
+use ansi_term::{Color, Style};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom, f64::consts::PI, fmt::Display};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    /// The 6 colours every terminal supports.
+    #[default]
+    Basic,
+    /// Colours from the 256 colour table.
+    Ansi256,
+    /// As many colours as needed, evenly spread around the colour wheel.
+    TrueColor,
+    /// Colours that remain distinct for the most common kinds of colour
+    /// blindness.
+    ColorBlind,
+    /// No colours, only patterns.
+    None,
+}
+
+const BASIC: [Color; 6] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Purple,
+    Color::Cyan,
+];
+
+const ANSI_256: [u8; 18] = [
+    196, 46, 226, 33, 201, 51, 208, 118, 129, 214, 39, 160, 84, 99, 220, 27, 168, 35,
+];
+
+/// The Okabe-Ito palette.
+const COLOR_BLIND: [(u8, u8, u8); 7] = [
+    (230, 159, 0),
+    (86, 180, 233),
+    (0, 158, 115),
+    (240, 228, 66),
+    (0, 114, 178),
+    (213, 94, 0),
+    (204, 121, 167),
+];
+
+/// Fillers for the empty space of a cell, used when there are no colours left.
+const PATTERNS: [char; 8] = ['░', '▒', '▓', '#', '*', '+', '=', '~'];
+
+/// The `n`th pattern: each of [`PATTERNS`] alone, then every pair of two
+/// different ones, which only repeat after 64.
+fn pattern(n: usize) -> (char, Option<char>) {
+    let len = PATTERNS.len();
+    match n.checked_sub(len) {
+        None => (PATTERNS[n], None),
+        Some(k) => {
+            let (first, offset) = ((k / (len - 1)) % len, k % (len - 1) + 1);
+            (PATTERNS[first], Some(PATTERNS[(first + offset) % len]))
+        }
+    }
+}
+
+impl Palette {
+    /// The colours of this palette, `None` if it has as many as needed.
+    fn colors(self) -> Option<Vec<Color>> {
+        match self {
+            Self::Basic => Some(BASIC.to_vec()),
+            Self::Ansi256 => Some(ANSI_256.iter().map(|c| Color::Fixed(*c)).collect()),
+            Self::ColorBlind => Some(
+                COLOR_BLIND
+                    .iter()
+                    .map(|(r, g, b)| Color::RGB(*r, *g, *b))
+                    .collect(),
+            ),
+            Self::TrueColor => None,
+            Self::None => Some(Vec::new()),
+        }
+    }
+}
+
+/// A colour picked by the user, written as a name (`red`), an entry of the 256
+/// colour table (`208`) or in hex (`#ff8800`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CourseColor(pub Color);
+
+impl TryFrom<String> for CourseColor {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let named = match s.to_ascii_lowercase().as_str() {
+            "black" => Some(Color::Black),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "purple" => Some(Color::Purple),
+            "cyan" => Some(Color::Cyan),
+            "white" => Some(Color::White),
+            _ => None,
+        };
+        let hex = |i: usize| s.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok());
+        named
+            .or_else(|| s.parse().ok().map(Color::Fixed))
+            .or_else(|| match (s.len(), s.starts_with('#')) {
+                (7, true) => Some(Color::RGB(hex(1)?, hex(3)?, hex(5)?)),
+                _ => None,
+            })
+            .map(CourseColor)
+            .ok_or_else(|| format!("Invalid colour: {}", s))
+    }
+}
+
+impl From<CourseColor> for String {
+    fn from(c: CourseColor) -> Self {
+        match c.0 {
+            Color::Black => "black".into(),
+            Color::Red => "red".into(),
+            Color::Green => "green".into(),
+            Color::Yellow => "yellow".into(),
+            Color::Blue => "blue".into(),
+            Color::Purple => "purple".into(),
+            Color::Cyan => "cyan".into(),
+            Color::White => "white".into(),
+            Color::Fixed(n) => n.to_string(),
+            Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub palette: Palette,
+    /// Colours that override the palette for specific courses.
+    pub courses: BTreeMap<String, CourseColor>,
+}
+
+/// How the cells of one course are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CourseStyle {
+    pub color: Option<Color>,
+    /// What to fill the rest of a cell with, instead of spaces. At least one
+    /// column is left for it, shortening the text if needed.
+    pub pattern: Option<char>,
+    /// Takes turns with `pattern`, for courses that share it with another. Two
+    /// columns are left then.
+    pub second_pattern: Option<char>,
+}
+
+impl CourseStyle {
+    /// Theory classes are drawn over the colour, labs in it.
+    pub fn paint<T: Display>(&self, theory: bool, text: T) -> String {
+        match self.color {
+            Some(c) if theory => Style::new().on(c).paint(text.to_string()).to_string(),
+            Some(c) => Style::new().fg(c).paint(text.to_string()).to_string(),
+            None => text.to_string(),
+        }
+    }
+
+    /// `width` columns of the pattern, or of spaces without one.
+    pub fn fill(&self, width: usize) -> String {
+        let first = self.pattern.unwrap_or(' ');
+        let second = self.second_pattern.unwrap_or(first);
+        (0..width)
+            .map(|i| if i % 2 == 0 { first } else { second })
+            .collect()
+    }
+}
+
+impl Theme {
+    /// A theme without colours, for when `NO_COLOR` is set.
+    pub fn no_color() -> Self {
+        Self {
+            palette: Palette::None,
+            courses: BTreeMap::new(),
+        }
+    }
+
+    /// The style of cells that need attention, like the half hours where
+    /// compared timetables differ.
+    pub fn highlight(&self) -> CourseStyle {
+        CourseStyle {
+            color: Some(Color::Red).filter(|_| self.palette != Palette::None),
+            pattern: Some('!'),
+            second_pattern: None,
+        }
+    }
+
+    /// The style of each of `courses`, which should be every course of the
+    /// schedule, so that a course looks the same in every timetable.
+    pub fn styles<'a, I>(&self, courses: I) -> BTreeMap<&'a str, CourseStyle>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let palette = self.palette.colors();
+        let mut auto = 0;
+        courses
+            .into_iter()
+            .map(|course| {
+                let style = match (self.palette, self.courses.get(course)) {
+                    (Palette::None, _) => {
+                        let (pattern, second_pattern) = pattern(auto);
+                        CourseStyle {
+                            color: None,
+                            pattern: Some(pattern),
+                            second_pattern,
+                        }
+                    }
+                    (_, Some(c)) => CourseStyle {
+                        color: Some(c.0),
+                        pattern: None,
+                        second_pattern: None,
+                    },
+                    (_, None) => match &palette {
+                        Some(colors) => {
+                            let pattern = (auto / colors.len()).checked_sub(1).map(pattern);
+                            CourseStyle {
+                                color: Some(colors[auto % colors.len()]),
+                                pattern: pattern.map(|p| p.0),
+                                second_pattern: pattern.and_then(|p| p.1),
+                            }
+                        }
+                        None => CourseStyle {
+                            color: Some(hue(auto)),
+                            pattern: None,
+                            second_pattern: None,
+                        },
+                    },
+                };
+                if self.palette == Palette::None || !self.courses.contains_key(course) {
+                    auto += 1;
+                }
+                (course, style)
+            })
+            .collect()
+    }
+}
+
+/// The `n`th colour of a sequence where consecutive colours are far apart on
+/// the colour wheel, by stepping the hue by the golden angle.
+fn hue(n: usize) -> Color {
+    let h = (n as f64 * 0.618_033_988_75).fract() * 2.0 * PI;
+    let channel = |offset: f64| (((h + offset).cos() * 0.5 + 0.5) * 200.0 + 40.0) as u8;
+    Color::RGB(
+        channel(0.0),
+        channel(-2.0 * PI / 3.0),
+        channel(2.0 * PI / 3.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const COURSES: [&str; 8] = ["A", "B", "C", "D", "E", "F", "G", "H"];
+
+    #[test]
+    fn patterns_once_colours_run_out() {
+        let styles = Theme::default().styles(COURSES.iter().copied());
+        assert_eq!(styles["A"].color, Some(Color::Red));
+        assert_eq!(styles["A"].pattern, None);
+        assert_eq!(styles["F"].pattern, None);
+        assert_eq!(styles["G"].color, Some(Color::Red));
+        assert_eq!(styles["G"].pattern, Some(PATTERNS[0]));
+        assert_ne!(styles["G"], styles["A"]);
+    }
+
+    #[test]
+    fn picked_colours() {
+        let theme = serde_json::from_str::<Theme>(
+            r##"{ "palette": "Ansi256", "courses": { "B": "#ff8800", "C": "blue" } }"##,
+        )
+        .unwrap();
+        let styles = theme.styles(COURSES.iter().copied());
+        assert_eq!(styles["A"].color, Some(Color::Fixed(ANSI_256[0])));
+        assert_eq!(styles["B"].color, Some(Color::RGB(0xff, 0x88, 0x00)));
+        assert_eq!(styles["C"].color, Some(Color::Blue));
+        // Picked colours don't use up the palette.
+        assert_eq!(styles["D"].color, Some(Color::Fixed(ANSI_256[1])));
+        assert_eq!(String::from(CourseColor(Color::RGB(1, 2, 3))), "#010203");
+        assert!(CourseColor::try_from("#12345".to_string()).is_err());
+    }
+
+    #[test]
+    fn no_color() {
+        let styles = Theme::no_color().styles(COURSES.iter().copied());
+        assert!(styles.values().all(|s| s.color.is_none()));
+        assert_eq!(styles["A"].pattern, Some(PATTERNS[0]));
+        assert_eq!(styles["B"].pattern, Some(PATTERNS[1]));
+        assert_eq!(styles["A"].paint(true, "T1 A"), "T1 A");
+        assert_eq!(Theme::no_color().highlight().color, None);
+        assert_ne!(hue(0), hue(1));
+    }
+
+    #[test]
+    fn two_patterns_once_they_run_out() {
+        let courses = (0..64).map(|n| format!("C{}", n)).collect::<Vec<_>>();
+        let styles = Theme::no_color().styles(courses.iter().map(String::as_str));
+        let fills = styles.values().map(|s| s.fill(4)).collect::<BTreeSet<_>>();
+        assert_eq!(fills.len(), 64);
+        assert_eq!(styles["C7"].second_pattern, None);
+        assert_eq!(styles["C8"].fill(4), "░▒░▒");
+        assert_eq!(styles["C9"].fill(3), "░▓░");
+        assert_eq!(pattern(64), pattern(8));
+        let styles = Theme::default().styles(courses.iter().map(String::as_str));
+        assert_eq!(styles["C53"].color, Some(Color::Cyan));
+        assert_eq!(styles["C53"].fill(2), "~~");
+        assert_eq!(styles["C54"].fill(2), "░▒");
+    }
+}
@@ -1,120 +1,191 @@
 use crate::{
-    shifts::{ClassGroup, Shifts},
-    util::{Class, ClassType, WeekDay, ALL_DAYS, WEEKDAYS},
-};
-use ansi_term::{
-    Color::{self, *},
-    Style,
+    matrix,
+    render::{Cell, Grid, RenderOptions},
+    shifts::{ClassSet, Shift, Shifts},
+    theme::{CourseStyle, Theme},
+    util::{ClassType, WeekDay, WEEKDAYS},
 };
 use chrono::{NaiveTime, Timelike};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::{self, Display},
-    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
 };
-use unicode_width::UnicodeWidthStr;
 
-const COLORS: [Color; 6] = [Red, Green, Yellow, Blue, Purple, Cyan];
+/// Number of half hours in a day, each one is a bit of a day's occupancy.
+pub const SLOTS: usize = 24 * 2;
+
+/// Identifies a timetable by the shifts it's made of, grouped by course, so it
+/// can be found again after the filters, the ranking or the program change.
+pub type TimetableKey = BTreeMap<String, BTreeSet<ClassType>>;
 
-#[derive(Clone)]
-pub struct TimeTable([[TimeBlock; 24 * 2]; WEEKDAYS], HashMap<String, Color>);
+/// Where and between which two shifts a class failed to fit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub weekday: WeekDay,
+    /// When both classes happen.
+    pub times: [NaiveTime; 2],
+    /// The course and shift of the class it overlaps, then of the class that
+    /// didn't fit, which are the same for a shift that overlaps itself.
+    pub shifts: [(String, ClassType); 2],
+}
 
-impl Default for TimeTable {
-    fn default() -> Self {
-        Self(
-            array_init::array_init(|_| array_init::array_init(|_| TimeBlock::default())),
-            HashMap::default(),
-        )
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [(a, k), (b, l)] = &self.shifts;
+        let [start, end] = self.times;
+        write!(
+            f,
+            "{} {}-{}: {} {}",
+            self.weekday,
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            a,
+            k.code()
+        )?;
+        if (a, k) != (b, l) {
+            write!(f, " and {} {}", b, l.code())?;
+        }
+        Ok(())
     }
 }
 
+/// A choice of shifts where no two classes overlap.
+///
+/// Which half hours are taken is kept as one bitmask per day, so checking for
+/// conflicts and most filters are a couple of bitwise operations. The grid is
+/// only rebuilt from the shifts when rendering.
+#[derive(Debug, Default, Clone)]
+pub struct TimeTable {
+    days: [u64; WEEKDAYS],
+    shifts: Vec<Arc<Shift>>,
+}
+
 impl TimeTable {
-    pub fn add(
-        mut self,
-        class: &Class,
-    ) -> Result<TimeTable, (WeekDay, [NaiveTime; 2], (String, String))> {
-        static ID: AtomicU64 = AtomicU64::new(0);
-        assert!(class.start.minute() == 0 || class.start.minute() == 30);
-        assert!(class.end.minute() == 0 || class.end.minute() == 30);
-        let start = time_to_index(class.start);
-        let end = time_to_index(class.end);
-        if let Some(TimeBlock::Filled(_, s, _)) = self.0[class.weekday as usize][start..end]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, shift: &Arc<Shift>) -> Result<TimeTable, Conflict> {
+        self.try_push(shift)?;
+        Ok(self)
+    }
+
+    fn try_push(&mut self, shift: &Arc<Shift>) -> Result<(), Conflict> {
+        let clash = self
+            .days
             .iter()
-            .find(|b| **b != TimeBlock::Empty)
-        {
-            let e = (
-                class.weekday,
-                [TimeBlock::to_time(start), TimeBlock::to_time(end)],
-                (s.to_string(), class.name.to_string()),
-            );
-            Err(e)
-        } else {
-            let id = ID.fetch_add(1, Ordering::Relaxed);
-            self.0[class.weekday as usize][start..end]
+            .zip(shift.occupancy())
+            .any(|(taken, new)| taken & new != 0);
+        if clash || shift.overlaps_itself() {
+            return Err(self.conflict(shift));
+        }
+        self.days
+            .iter_mut()
+            .zip(shift.occupancy())
+            .for_each(|(taken, new)| *taken |= new);
+        self.shifts.push(Arc::clone(shift));
+        Ok(())
+    }
+
+    fn pop(&mut self) {
+        if let Some(shift) = self.shifts.pop() {
+            self.days
                 .iter_mut()
-                .for_each(|b| *b = TimeBlock::Filled(class.kind, class.name.to_string(), id));
-            if !self.1.contains_key(&class.name) {
-                let n = self.1.len();
-                self.1.insert(class.name.to_string(), COLORS[n]);
-            }
-            Ok(self)
+                .zip(shift.occupancy())
+                .for_each(|(taken, new)| *taken &= !new);
         }
     }
 
+    fn conflict(&self, shift: &Shift) -> Conflict {
+        let placed = self.shifts.iter().flat_map(|s| s.classes.iter());
+        shift
+            .classes
+            .iter()
+            .flat_map(|c| {
+                placed
+                    .clone()
+                    .chain(shift.classes.iter().filter(move |o| !std::ptr::eq(*o, c)))
+                    .map(move |o| (c, o))
+            })
+            .find(|(c, o)| c.weekday == o.weekday && c.start < o.end && o.start < c.end)
+            .map(|(c, o)| Conflict {
+                weekday: c.weekday,
+                times: [c.start.max(o.start), c.end.min(o.end)],
+                shifts: [(o.name.clone(), o.kind), (c.name.clone(), c.kind)],
+            })
+            .expect("A conflict has two overlapping classes")
+    }
+
+    /// Every timetable where no two classes overlap.
+    ///
+    /// Shifts that clash with every shift of some other course are left out
+    /// before searching, see [`matrix`](crate::matrix).
+    ///
+    /// With the `parallel` feature the search is split between threads by the
+    /// shift picked for the first course, but the result is in the same order
+    /// either way.
     pub fn all_the_combos(schedule: &Shifts) -> Vec<Self> {
-        let classes = schedule.class_set();
-        let courses = classes.keys().map(|x| *x).collect::<Vec<(&str, bool)>>();
-        fn gather<'a>(
-            classes: &HashMap<(&'a str, bool), HashMap<i64, ClassGroup>>,
-            timetable: TimeTable,
-            course: &(&'a str, bool),
-            other_courses: &[(&str, bool)],
-        ) -> Vec<TimeTable> {
-            classes[course]
-                .values()
-                .map(|class_block| {
-                    class_block
-                        .iter()
-                        .try_fold(timetable.clone(), |acc, x| acc.add(x))
-                })
-                .filter_map(Result::ok)
-                .map(|timetable| match other_courses.get(0) {
-                    Some(next) => gather(classes, timetable.clone(), next, &other_courses[1..]),
-                    None => vec![timetable],
-                })
-                .flatten()
-                .collect()
+        let mut classes = schedule.class_set();
+        matrix::prune(&mut classes);
+        let courses = classes.keys().copied().collect::<Vec<(&str, bool)>>();
+        fn gather(
+            classes: &ClassSet,
+            timetable: &mut TimeTable,
+            courses: &[(&str, bool)],
+            out: &mut Vec<TimeTable>,
+        ) {
+            match courses.split_first() {
+                Some((course, other_courses)) => {
+                    for shift in &classes[course] {
+                        if timetable.try_push(shift).is_ok() {
+                            gather(classes, timetable, other_courses, out);
+                            timetable.pop();
+                        }
+                    }
+                }
+                None => out.push(timetable.clone()),
+            }
         }
-        courses
-            .get(0)
-            .map(|first| gather(&classes, TimeTable::default(), first, &courses[1..]))
-            .unwrap_or_else(Vec::new)
+        let (first, other_courses) = match courses.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+        let branch = |shift: &Arc<Shift>| {
+            let mut out = Vec::new();
+            let mut timetable = TimeTable::default();
+            if timetable.try_push(shift).is_ok() {
+                gather(&classes, &mut timetable, other_courses, &mut out);
+            }
+            out
+        };
+        #[cfg(feature = "parallel")]
+        let out = classes[first].par_iter().flat_map_iter(branch).collect();
+        #[cfg(not(feature = "parallel"))]
+        let out = classes[first].iter().flat_map(branch).collect();
+        out
     }
 
     pub fn starts_after(&self, time: NaiveTime) -> bool {
-        let idx = time_to_index(time);
-        dbg!(idx, time);
-        self.0
-            .iter()
-            .all(|day| day[..idx].iter().all(|b| *b == TimeBlock::Empty))
+        let before = slot_mask(NaiveTime::from_hms(0, 0, 0), time);
+        self.days.iter().all(|day| day & before == 0)
     }
 
+    /// Whether nothing is on after the half hour starting at `time`, which
+    /// may still have a class.
     pub fn ends_before(&self, time: NaiveTime) -> bool {
-        let idx = time_to_index(time);
-        self.0
-            .iter()
-            .all(|day| day[(idx + 1)..].iter().all(|b| *b == TimeBlock::Empty))
+        let after = u64::MAX << (time_to_index(time) + 1);
+        self.days.iter().all(|day| day & after == 0)
     }
 
     pub fn free_day(&self, d: WeekDay) -> bool {
-        self.0[d as usize].iter().all(|b| *b == TimeBlock::Empty)
+        self.days[d as usize] == 0
     }
 
     pub fn has_the_shift(&self, kind: ClassType, name: &str) -> bool {
-        self.0
+        self.shifts
             .iter()
-            .flat_map(|x| x.iter())
-            .any(|x| *x == (kind, name))
+            .any(|s| s.kind == kind && s.course == name)
     }
 
     pub fn hasnt_the_shift(&self, kind: ClassType, name: &str) -> bool {
@@ -122,132 +193,164 @@ impl TimeTable {
     }
 
     pub fn sum_work_hours(&self) -> isize {
-        self.0
+        self.days
             .iter()
-            .map(|day| {
-                let first = day
-                    .iter()
-                    .enumerate()
-                    .find(|(_, b)| **b != TimeBlock::Empty)
-                    .map(|(i, _)| i);
-                let last = day
-                    .iter()
-                    .enumerate()
-                    .rev()
-                    .find(|(_, b)| **b != TimeBlock::Empty)
-                    .map(|(i, _)| i);
-                if let (Some(f), Some(l)) = (first, last) {
-                    l - f
-                } else {
-                    0
-                }
-            })
-            .sum::<usize>() as isize
+            .filter(|day| **day != 0)
+            .map(|day| (63 - day.leading_zeros() - day.trailing_zeros()) as isize)
+            .sum()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = impl Iterator<Item = (usize, ClassType, &str)>> {
-        self.0.iter().map(|day| {
-            day.iter().enumerate().filter_map(|(i, x)| {
-                if let TimeBlock::Filled(t, c, ..) = x {
-                    Some((i, *t, c.as_str()))
-                } else {
-                    None
-                }
-            })
+    /// The half hours taken in each day, one bit per half hour, with the least
+    /// significant bit being 00h00.
+    pub fn occupancy(&self) -> &[u64; WEEKDAYS] {
+        &self.days
+    }
+
+    /// The shifts this timetable is made of, grouped by course.
+    pub fn shifts(&self) -> BTreeMap<&str, BTreeSet<ClassType>> {
+        self.shifts.iter().fold(BTreeMap::new(), |mut acc, shift| {
+            acc.entry(shift.course.as_str())
+                .or_insert_with(BTreeSet::new)
+                .insert(shift.kind);
+            acc
         })
     }
-}
 
-impl From<&Shifts> for Vec<TimeTable> {
-    fn from(s: &Shifts) -> Self {
-        TimeTable::all_the_combos(s)
+    pub fn key(&self) -> TimetableKey {
+        self.shifts()
+            .into_iter()
+            .map(|(course, kinds)| (course.to_string(), kinds))
+            .collect()
     }
-}
 
-impl Display for TimeTable {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let max_width = self
-            .0
+    /// Rebuilds the timetable identified by `key` from the shifts of
+    /// `schedule`, `None` if some of them aren't in it anymore or they overlap.
+    pub fn from_key(schedule: &Shifts, key: &TimetableKey) -> Option<Self> {
+        key.iter()
+            .flat_map(|(course, kinds)| kinds.iter().map(move |k| (course, *k)))
+            .try_fold(TimeTable::default(), |t, (course, kind)| {
+                let shift = schedule
+                    .shifts()
+                    .find(|s| s.course == *course && s.kind == kind)?;
+                t.add(shift).ok()
+            })
+    }
+
+    /// Whether this is the timetable identified by `key`.
+    pub fn matches(&self, key: &TimetableKey) -> bool {
+        self.shifts()
             .iter()
-            .flat_map(|x| x.iter().map(|s| s.width()))
-            .max()
-            .ok_or(fmt::Error)?;
-        let mut first = 48;
-        let mut last = 48;
-        for i in 0..(24 * 2) {
-            if self.0.iter().any(|x| x[i] != TimeBlock::Empty) {
-                first = i;
-                break;
-            }
-        }
-        for i in (0..(24 * 2)).rev() {
-            if self.0.iter().any(|x| x[i] != TimeBlock::Empty) {
-                last = i + 1;
-                break;
-            }
-        }
-        for i in first..last {
-            write!(f, "{} ", TimeBlock::to_time(i).format("%H:%M"))?;
-            for day in &ALL_DAYS {
-                self.0[*day as usize][i].display(&self.1, max_width, f)?
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+            .map(|(course, kinds)| (*course, kinds))
+            .eq(key.iter().map(|(course, kinds)| (course.as_str(), kinds)))
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum TimeBlock {
-    Empty,
-    Filled(ClassType, String, u64),
-}
+    /// The shifts this timetable is made of, in the order they were picked.
+    pub fn chosen(&self) -> &[Arc<Shift>] {
+        &self.shifts
+    }
 
-impl TimeBlock {
-    fn to_time(u: usize) -> NaiveTime {
-        index_to_time(u)
+    /// Every class in this timetable, ordered by day and start time.
+    pub fn sessions(&self) -> Vec<Session<'_>> {
+        let mut sessions = self
+            .shifts
+            .iter()
+            .flat_map(|s| s.classes.iter())
+            .map(|c| Session {
+                weekday: c.weekday,
+                kind: c.kind,
+                course: &c.name,
+                start: c.start,
+                end: c.end,
+            })
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|s| (s.weekday, s.start));
+        sessions
     }
 
-    fn width(&self) -> usize {
-        match self {
-            Self::Empty => 0,
-            Self::Filled(_, s, _) => 2 + s.as_str().width(),
-        }
+    pub fn metrics(&self) -> Metrics {
+        self.days
+            .iter()
+            .filter(|day| **day != 0)
+            .fold(Metrics::default(), |mut m, day| {
+                let first = day.trailing_zeros() as usize;
+                let last = 63 - day.leading_zeros() as usize;
+                let filled = day.count_ones();
+                let span = (last + 1 - first) as u32;
+                m.days_on_campus += 1;
+                m.class_minutes += filled * 30;
+                m.campus_minutes += span * 30;
+                m.gap_minutes += (span - filled) * 30;
+                let (start, end) = (index_to_time(first), index_to_time(last + 1));
+                m.earliest_start = Some(m.earliest_start.map_or(start, |t| t.min(start)));
+                m.latest_end = Some(m.latest_end.map_or(end, |t| t.max(end)));
+                m
+            })
     }
 
-    fn display(
-        &self,
-        color_map: &HashMap<String, Color>,
-        width: usize,
-        f: &mut fmt::Formatter,
-    ) -> fmt::Result {
-        match self {
-            Self::Empty => (0..(width + 3)).try_for_each(|_| write!(f, " ")),
-            Self::Filled(t, s, _) => {
-                let style = match t {
-                    ClassType::T(_) => Style::new().on(color_map[s]),
-                    ClassType::L(_) => Style::new().fg(color_map[s]),
-                };
-                write!(f, "{}", style.paint(format!("{} {}", t, s)))?;
-                (0..(width - s.width())).try_for_each(|_| write!(f, " "))
+    /// Lays this timetable out as a grid, with the given style for each
+    /// course, as made by [`Theme::styles`]. Courses without a style are drawn
+    /// plainly.
+    pub fn grid(&self, styles: &BTreeMap<&str, CourseStyle>) -> Grid {
+        let plain = CourseStyle {
+            color: None,
+            pattern: None,
+            second_pattern: None,
+        };
+        let style = |course: &str| styles.get(course).copied().unwrap_or(plain);
+        let mut grid = Grid::default();
+        for s in self.sessions() {
+            let cell = Cell {
+                text: format!("{} {}", s.kind, s.course),
+                style: style(s.course),
+                theory: matches!(s.kind, ClassType::T(_)),
+            };
+            for i in time_to_index(s.start)..time_to_index(s.end) {
+                grid.set(s.weekday, i, cell.clone());
             }
         }
+        for course in self.shifts().into_keys() {
+            grid.add_legend(course, style(course));
+        }
+        grid
+    }
+
+    pub fn render(&self, styles: &BTreeMap<&str, CourseStyle>, options: &RenderOptions) -> String {
+        self.grid(styles).render(options)
     }
 }
 
-impl Default for TimeBlock {
-    fn default() -> Self {
-        Self::Empty
+/// A single class, as placed in a [`TimeTable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session<'a> {
+    pub weekday: WeekDay,
+    pub kind: ClassType,
+    pub course: &'a str,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Summary numbers used to compare timetables.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub days_on_campus: u32,
+    pub class_minutes: u32,
+    /// Time between the first and the last class of each day, summed.
+    pub campus_minutes: u32,
+    pub gap_minutes: u32,
+    pub earliest_start: Option<NaiveTime>,
+    pub latest_end: Option<NaiveTime>,
+}
+
+impl From<&Shifts> for Vec<TimeTable> {
+    fn from(s: &Shifts) -> Self {
+        TimeTable::all_the_combos(s)
     }
 }
 
-impl PartialEq<(ClassType, &'_ str)> for TimeBlock {
-    fn eq(&self, other: &(ClassType, &'_ str)) -> bool {
-        if let TimeBlock::Filled(t, n, _) = self {
-            *t == other.0 && n == other.1
-        } else {
-            false
-        }
+impl Display for TimeTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let styles = Theme::default().styles(self.shifts().into_keys());
+        write!(f, "{}", self.render(&styles, &RenderOptions::default()))
     }
 }
 
@@ -258,3 +361,184 @@ pub fn time_to_index(t: NaiveTime) -> usize {
 pub fn index_to_time(u: usize) -> NaiveTime {
     NaiveTime::from_hms((u / 2) as u32, (u % 2) as u32 * 30, 0)
 }
+
+/// The bits of the half hours between `start` and `end`.
+pub fn slot_mask(start: NaiveTime, end: NaiveTime) -> u64 {
+    let (start, end) = (time_to_index(start), time_to_index(end));
+    if start >= end {
+        0
+    } else {
+        (u64::MAX >> (64 - (end - start))) << start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::ALL_DAYS;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms(h, m, 0)
+    }
+
+    fn schedule() -> Shifts {
+        "A:T1:10h00:12h00:Mon\n\
+         A:L1:14h00:15h00:Mon\n\
+         B:T1:09h00:10h00:Tue\n\
+         B:T1:11h00:11h30:Tue\n\
+         C:T1:10h30:11h00:Mon"
+            .parse()
+            .unwrap()
+    }
+
+    fn shift<'a>(schedule: &'a Shifts, course: &str, kind: ClassType) -> &'a Arc<Shift> {
+        schedule
+            .shifts()
+            .find(|s| s.course == course && s.kind == kind)
+            .unwrap()
+    }
+
+    fn timetable(schedule: &Shifts, shifts: &[(&str, ClassType)]) -> TimeTable {
+        shifts
+            .iter()
+            .fold(TimeTable::default(), |t, (course, kind)| {
+                t.add(shift(schedule, course, *kind)).unwrap()
+            })
+    }
+
+    #[test]
+    fn masks() {
+        assert_eq!(slot_mask(time(10, 0), time(12, 0)), 0b1111 << 20);
+        assert_eq!(slot_mask(time(23, 30), time(0, 0)), 0);
+        assert_eq!(slot_mask(time(0, 0), time(0, 30)), 1);
+        assert_eq!(time_to_index(time(9, 30)), 19);
+        assert_eq!(index_to_time(19), time(9, 30));
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let schedule = schedule();
+        let mut t = timetable(&schedule, &[("A", ClassType::T(1))]);
+        let before = *t.occupancy();
+        let c = shift(&schedule, "C", ClassType::T(1));
+        assert_eq!(
+            t.try_push(c),
+            Err(Conflict {
+                weekday: WeekDay::Mon,
+                times: [time(10, 30), time(11, 0)],
+                shifts: [
+                    ("A".to_string(), ClassType::T(1)),
+                    ("C".to_string(), ClassType::T(1))
+                ],
+            })
+        );
+        assert_eq!(*t.occupancy(), before);
+        assert_eq!(t.chosen().len(), 1);
+        t.try_push(shift(&schedule, "B", ClassType::T(1))).unwrap();
+        assert_eq!(t.occupancy()[WeekDay::Tue as usize], 0b10011 << 18);
+        t.pop();
+        assert_eq!(*t.occupancy(), before);
+        assert_eq!(t.chosen().len(), 1);
+    }
+
+    // `sum_work_hours` and the filters give what they did before occupancy
+    // was kept as bitmasks.
+    #[test]
+    fn metrics_and_filters() {
+        let schedule = schedule();
+        let t = timetable(
+            &schedule,
+            &[
+                ("A", ClassType::T(1)),
+                ("A", ClassType::L(1)),
+                ("B", ClassType::T(1)),
+            ],
+        );
+        assert_eq!(t.sum_work_hours(), 13);
+        assert_eq!(
+            t.metrics(),
+            Metrics {
+                days_on_campus: 2,
+                class_minutes: 270,
+                campus_minutes: 450,
+                gap_minutes: 180,
+                earliest_start: Some(time(9, 0)),
+                latest_end: Some(time(15, 0)),
+            }
+        );
+        assert!(t.starts_after(time(9, 0)));
+        assert!(!t.starts_after(time(9, 30)));
+        assert!(t.ends_before(time(15, 0)));
+        assert!(t.ends_before(time(14, 30)));
+        assert!(!t.ends_before(time(14, 0)));
+        assert!(t.ends_before(time(23, 30)));
+        assert!(t.free_day(WeekDay::Wed));
+        assert!(!t.free_day(WeekDay::Tue));
+    }
+
+    #[test]
+    fn all_the_combos() {
+        // C T1 always clashes with A T1, the only theory shift of A.
+        assert!(TimeTable::all_the_combos(&schedule()).is_empty());
+        let schedule = "A:T1:10h00:12h00:Mon\nA:T2:10h00:12h00:Tue\nC:T1:10h30:11h00:Mon"
+            .parse()
+            .unwrap();
+        let all = TimeTable::all_the_combos(&schedule);
+        assert_eq!(all.len(), 1);
+        assert!(all[0].has_the_shift(ClassType::T(2), "A"));
+        assert!(all[0].has_the_shift(ClassType::T(1), "C"));
+    }
+
+    fn chosen(t: &TimeTable) -> BTreeSet<(String, ClassType)> {
+        t.chosen()
+            .iter()
+            .map(|s| (s.course.clone(), s.kind))
+            .collect()
+    }
+
+    #[test]
+    fn same_order_as_a_sequential_search() {
+        use itertools::Itertools;
+        let mut lines = Vec::new();
+        for (c, course) in ["A", "B", "C", "D"].iter().enumerate() {
+            for shift in 1..=4 {
+                let (day, hour) = ((c + shift) % 5, 8 + (c * 3 + shift) % 10);
+                let day = ALL_DAYS[day];
+                lines.push(format!(
+                    "{}:T{}:{:02}h00:{:02}h00:{}",
+                    course,
+                    shift,
+                    hour,
+                    hour + 2,
+                    day
+                ));
+                lines.push(format!(
+                    "{}:L{}:{:02}h30:{:02}h30:{}",
+                    course,
+                    shift,
+                    hour,
+                    hour + 1,
+                    ALL_DAYS[(day as usize + 2) % 5]
+                ));
+            }
+        }
+        let schedule: Shifts = lines.join("\n").parse().unwrap();
+        let sequential = schedule
+            .class_set()
+            .values()
+            .multi_cartesian_product()
+            .filter_map(|shifts| {
+                shifts
+                    .into_iter()
+                    .try_fold(TimeTable::default(), |t, s| t.add(s).ok())
+            })
+            .map(|t| chosen(&t))
+            .collect::<Vec<_>>();
+        let keys = TimeTable::all_the_combos(&schedule)
+            .iter()
+            .map(chosen)
+            .collect::<Vec<_>>();
+        assert!(!keys.is_empty());
+        assert_eq!(keys, sequential);
+    }
+}
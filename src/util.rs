@@ -9,7 +9,19 @@ use std::{
 
 pub const WEEKDAYS: usize = 7;
 
-#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, IntoEnumIterator)]
+#[derive(
+    Hash,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    IntoEnumIterator,
+)]
 pub enum WeekDay {
     Mon = 0,
     Tue = 1,
@@ -80,7 +92,7 @@ impl Display for WeekDay {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ClassType {
     T(u32),
     L(u32),
@@ -90,7 +102,15 @@ impl ClassType {
     pub fn unique_id(self) -> i64 {
         match self {
             Self::T(n) => n as i64,
-            Self::L(n) => (n as i64) * -1,
+            Self::L(n) => -(n as i64),
+        }
+    }
+
+    /// The shift as written in a schedule file, e.g. `L13`.
+    pub fn code(self) -> String {
+        match self {
+            Self::T(n) => format!("T{}", n),
+            Self::L(n) => format!("L{}", n),
         }
     }
 }
@@ -119,11 +139,32 @@ impl FromStr for ClassType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Class {
     pub weekday: WeekDay,
     pub kind: ClassType,
     pub start: NaiveTime,
     pub end: NaiveTime,
     pub name: String,
+    /// Who teaches the class, from the optional sixth column.
+    #[serde(default)]
+    pub teacher: Option<String>,
+    /// Where the class is, from the optional seventh column.
+    #[serde(default)]
+    pub room: Option<String>,
+    /// The curricular year of the course, from the optional eighth column.
+    #[serde(default)]
+    pub year: Option<u32>,
+    /// Line of the schedule file this class was read from, starting at 1.
+    pub line_no: usize,
+    /// The line exactly as it was read, extra columns included.
+    pub line: String,
+}
+
+impl Class {
+    /// The eighth column, when there is one that isn't a curricular year.
+    pub fn invalid_year(&self) -> Option<&str> {
+        let year = self.line.split(':').nth(7).map(str::trim)?;
+        (!year.is_empty() && self.year.is_none()).then_some(year)
+    }
 }
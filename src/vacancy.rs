@@ -0,0 +1,179 @@
+//! How many seats are left in each shift, kept in a local JSON file that can
+//! be edited and read again as they fill up:
+//!
+//! ```text
+//! { "PL": { "L1": 0, "L2": 3 }, "CPD": { "T1": 40 } }
+//! ```
+//!
+//! Shifts without seats left can't be picked, and shifts that aren't in the
+//! file are taken to have plenty.
+//!
+//! The risk of not getting a shift is a rough guess of `1 / (seats + 1)`, so a
+//! shift with one seat left is a coin toss, and a timetable's risk is that of
+//! not getting at least one of its shifts.
+
+use crate::{
+    shifts::{Shift, Shifts},
+    swap::{self, SwapPlan},
+    timetable::TimeTable,
+    util::ClassType,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::File, io, path::Path};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Vacancies {
+    /// Seats left by course and then by shift, as written in the schedule.
+    pub seats: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+/// What to do if a shift of the preferred timetable fills up.
+#[derive(Debug, Clone)]
+pub struct Fallback<'a> {
+    pub course: &'a str,
+    pub kind: ClassType,
+    pub seats: u32,
+    /// The closest timetable without the shift, if there is one.
+    pub plan: Option<SwapPlan<'a>>,
+}
+
+impl Vacancies {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Seats left in a shift, `None` when unknown.
+    pub fn seats(&self, course: &str, kind: ClassType) -> Option<u32> {
+        self.seats.get(course)?.get(&kind.code()).copied()
+    }
+
+    pub fn is_full(&self, shift: &Shift) -> bool {
+        self.seats(&shift.course, shift.kind) == Some(0)
+    }
+
+    /// `schedule` without the shifts that are full.
+    pub fn open_shifts(&self, schedule: &Shifts) -> Shifts {
+        schedule.retain_shifts(|s| !self.is_full(s))
+    }
+
+    /// The chance of not getting `shift`, from 0 to 1.
+    pub fn shift_risk(&self, shift: &Shift) -> f64 {
+        self.seats(&shift.course, shift.kind)
+            .map_or(0.0, |n| 1.0 / f64::from(n + 1))
+    }
+
+    /// The chance of not getting every shift of `timetable`, from 0 to 1.
+    pub fn risk(&self, timetable: &TimeTable) -> f64 {
+        1.0 - timetable
+            .chosen()
+            .iter()
+            .map(|s| 1.0 - self.shift_risk(s))
+            .product::<f64>()
+    }
+
+    /// Sorts `timetables` safest first, keeping the order of equally risky
+    /// ones, so sorting by a [`Ranking`](crate::Ranking) first breaks ties.
+    pub fn sort_by_risk(&self, timetables: &mut [&TimeTable]) {
+        timetables.sort_by_cached_key(|t| (self.risk(t) * 1e6) as u64);
+    }
+
+    /// For each shift of `preferred` whose seats are known, fewest seats first,
+    /// the closest of `timetables` that does without it.
+    pub fn fallbacks<'a>(
+        &self,
+        preferred: &'a TimeTable,
+        timetables: &[&'a TimeTable],
+    ) -> Vec<Fallback<'a>> {
+        let current = preferred.key();
+        let mut fallbacks = preferred
+            .chosen()
+            .iter()
+            .filter_map(|s| {
+                let seats = self.seats(&s.course, s.kind)?;
+                let plan = timetables
+                    .iter()
+                    .filter(|t| t.hasnt_the_shift(s.kind, &s.course))
+                    .map(|t| SwapPlan {
+                        timetable: t,
+                        swaps: swap::swaps_to(&current, t),
+                    })
+                    .min_by_key(|p| p.swaps.len());
+                Some(Fallback {
+                    course: &s.course,
+                    kind: s.kind,
+                    seats,
+                    plan,
+                })
+            })
+            .collect::<Vec<_>>();
+        fallbacks.sort_by_key(|f| f.seats);
+        fallbacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seats() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        B:T1:10h00:11h00:Wed"
+            .parse::<Shifts>()
+            .unwrap();
+        let vacancies =
+            serde_json::from_str::<Vacancies>(r#"{ "A": { "T1": 0, "T2": 1 }, "B": { "T1": 3 } }"#)
+                .unwrap();
+        assert_eq!(vacancies.seats("A", ClassType::T(2)), Some(1));
+        assert_eq!(vacancies.seats("A", ClassType::L(1)), None);
+        assert_eq!(vacancies.seats("C", ClassType::T(1)), None);
+        let open = vacancies
+            .open_shifts(&schedule)
+            .shifts()
+            .map(|s| (s.course.clone(), s.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            open,
+            vec![
+                ("A".to_string(), ClassType::T(2)),
+                ("B".to_string(), ClassType::T(1))
+            ]
+        );
+
+        let timetables = TimeTable::all_the_combos(&schedule);
+        assert_eq!(vacancies.risk(&timetables[0]), 1.0);
+        assert_eq!(vacancies.risk(&timetables[1]), 1.0 - 0.5 * 0.75);
+        assert_eq!(Vacancies::default().risk(&timetables[0]), 0.0);
+        let mut sorted = timetables.iter().collect::<Vec<_>>();
+        vacancies.sort_by_risk(&mut sorted);
+        assert_eq!(sorted[0].key(), timetables[1].key());
+    }
+
+    #[test]
+    fn fallbacks() {
+        let schedule = "A:T1:10h00:11h00:Mon\n\
+                        A:T2:10h00:11h00:Tue\n\
+                        B:T1:10h00:11h00:Wed"
+            .parse()
+            .unwrap();
+        let vacancies =
+            serde_json::from_str::<Vacancies>(r#"{ "A": { "T2": 1 }, "B": { "T1": 3 } }"#).unwrap();
+        let timetables = TimeTable::all_the_combos(&schedule);
+        let all = timetables.iter().collect::<Vec<_>>();
+        let fallbacks = vacancies.fallbacks(&timetables[1], &all);
+        let shifts = fallbacks
+            .iter()
+            .map(|f| (f.course, f.kind, f.seats))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shifts,
+            vec![("A", ClassType::T(2), 1), ("B", ClassType::T(1), 3)]
+        );
+        let plan = fallbacks[0].plan.as_ref().unwrap();
+        assert_eq!(plan.swaps[0].to_string(), "A T2 -> T1");
+        // Every timetable has B T1.
+        assert!(fallbacks[1].plan.is_none());
+    }
+}
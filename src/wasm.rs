@@ -0,0 +1,125 @@
+//! JavaScript bindings, for a browser front-end.
+//!
+//! Everything that crosses the boundary is either a plain number, a string or
+//! JSON in the same formats used by the rest of the crate: filters are read like
+//! saved filter files and timetables are written like the [`export`] module
+//! does.
+
+#[cfg(feature = "ical")]
+use crate::ical;
+use crate::{export, Ranking, Shifts, TimeTable, TimetableFilters};
+#[cfg(feature = "ical")]
+use chrono::NaiveDate;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Scheduler {
+    timetables: Vec<TimeTable>,
+    filters: TimetableFilters,
+    ranking: Ranking,
+}
+
+fn to_js<E: ToString>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+#[wasm_bindgen]
+impl Scheduler {
+    /// Parses a schedule and generates all its timetables.
+    #[wasm_bindgen(constructor)]
+    pub fn new(schedule: &str) -> Result<Scheduler, JsValue> {
+        let schedule = schedule.parse::<Shifts>().map_err(to_js)?;
+        let mut timetables = TimeTable::all_the_combos(&schedule);
+        let ranking = Ranking::default();
+        ranking.sort(&mut timetables);
+        Ok(Self {
+            timetables,
+            filters: TimetableFilters::default(),
+            ranking,
+        })
+    }
+
+    /// Replaces the filters, returns how many timetables remain.
+    #[wasm_bindgen(js_name = setFilters)]
+    pub fn set_filters(&mut self, filters: &str) -> Result<usize, JsValue> {
+        self.filters = serde_json::from_str(filters).map_err(to_js)?;
+        Ok(self.remaining().len())
+    }
+
+    /// Changes the order of the timetables, by name of a [`Ranking`] variant.
+    #[wasm_bindgen(js_name = setRanking)]
+    pub fn set_ranking(&mut self, ranking: &str) -> Result<(), JsValue> {
+        self.ranking = serde_json::from_value(serde_json::Value::String(ranking.to_string()))
+            .map_err(to_js)?;
+        self.ranking.sort(&mut self.timetables);
+        Ok(())
+    }
+
+    /// How many timetables remain after filtering.
+    pub fn count(&self) -> usize {
+        self.remaining().len()
+    }
+
+    /// A page of the remaining timetables, as JSON, with between 1 and
+    /// [`export::MAX_PER_PAGE`] timetables per page.
+    pub fn page(&self, page: usize, per_page: usize) -> Result<String, JsValue> {
+        if !(1..=export::MAX_PER_PAGE).contains(&per_page) {
+            return Err(to_js(format!(
+                "Expected between 1 and {} timetables per page",
+                export::MAX_PER_PAGE
+            )));
+        }
+        let mut out = Vec::new();
+        export::write_json_page(&mut out, &self.remaining(), page, per_page).map_err(to_js)?;
+        String::from_utf8(out).map_err(to_js)
+    }
+
+    /// The `n`th remaining timetable as an HTML page.
+    pub fn html(&self, n: usize) -> Result<String, JsValue> {
+        let mut out = Vec::new();
+        export::write_html(&mut out, self.get(n)?).map_err(to_js)?;
+        String::from_utf8(out).map_err(to_js)
+    }
+
+    /// The `n`th remaining timetable as an iCal calendar, with dates in the
+    /// `YYYY-MM-DD` format.
+    #[cfg(feature = "ical")]
+    pub fn ical(&self, n: usize, start: &str, end: &str) -> Result<String, JsValue> {
+        let start = start.parse::<NaiveDate>().map_err(to_js)?;
+        let end = end.parse::<NaiveDate>().map_err(to_js)?;
+        let mut out = Vec::new();
+        ical::write_cal(&mut out, self.get(n)?, start, end).map_err(to_js)?;
+        String::from_utf8(out).map_err(to_js)
+    }
+}
+
+impl Scheduler {
+    fn remaining(&self) -> Vec<&TimeTable> {
+        self.filters.apply(&self.timetables)
+    }
+
+    fn get(&self, n: usize) -> Result<&TimeTable, JsValue> {
+        self.remaining()
+            .get(n)
+            .copied()
+            .ok_or_else(|| to_js("No such timetable"))
+    }
+}
+
+// Only the paths that don't create a `JsValue` can run outside of a browser.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_and_pages() {
+        let mut scheduler = Scheduler::new("A:T1:10h00:11h00:Mon\nA:T2:11h00:12h00:Fri").unwrap();
+        assert_eq!(scheduler.count(), 2);
+        let filters = r#"{"starts_after":null,"ends_before":null,"free_days":["Fri"],"has_the_shift":[],"hasnt_the_shift":[]}"#;
+        assert_eq!(scheduler.set_filters(filters).unwrap(), 1);
+        let page =
+            serde_json::from_str::<serde_json::Value>(&scheduler.page(0, 10).unwrap()).unwrap();
+        assert_eq!(page["total"], 1);
+        assert!(scheduler.html(0).unwrap().contains("<table>"));
+    }
+}